@@ -111,7 +111,9 @@ test.describe('desktop runtime routing guardrails', () => {
       }) as typeof window.fetch;
 
       const previousTauri = globalWindow.__TAURI__;
-      globalWindow.__TAURI__ = { core: { invoke: () => Promise.resolve(null) } };
+      globalWindow.__TAURI__ = {
+        core: { invoke: (command: string) => Promise.resolve(command === 'get_local_api_port' ? 46123 : null) },
+      };
       delete globalWindow.__wmFetchPatched;
 
       try {
@@ -1,4 +1,5 @@
 #!/usr/bin/env node
+import { createHash, createHmac, timingSafeEqual } from 'node:crypto';
 import { createServer } from 'node:http';
 import { existsSync, readFileSync, writeFileSync } from 'node:fs';
 import { readdir } from 'node:fs/promises';
@@ -229,6 +230,12 @@ function resolveConfig(options = {}) {
     ].find((candidate) => existsSync(candidate)) ?? path.join(resourceDir, 'api');
   const mode = String(options.mode ?? process.env.LOCAL_API_MODE ?? 'desktop-sidecar');
   const cloudFallback = String(options.cloudFallback ?? process.env.LOCAL_API_CLOUD_FALLBACK ?? '') === 'true';
+  const secretKeys = new Set(
+    String(options.secretKeys ?? process.env.LOCAL_API_SECRET_KEYS ?? '')
+      .split(',')
+      .map((key) => key.trim())
+      .filter(Boolean),
+  );
   const logger = options.logger ?? console;
 
   return {
@@ -238,6 +245,7 @@ function resolveConfig(options = {}) {
     apiDir,
     mode,
     cloudFallback,
+    secretKeys,
     logger,
   };
 }
@@ -282,6 +290,37 @@ async function tryCloudFallback(requestUrl, req, context, reason) {
   }
 }
 
+function safeEqual(a, b) {
+  const left = Buffer.from(a);
+  const right = Buffer.from(b);
+  return left.length === right.length && timingSafeEqual(left, right);
+}
+
+// Accepts the sidecar token itself, or a per-window token minted by the desktop shell:
+// `wm1.<window>.<unix expiry>.<hex HMAC-SHA256(token, "wm1.<window>.<unix expiry>")>`.
+export function isAuthorizedToken(presented, expectedToken, nowMs = Date.now()) {
+  if (!presented) return false;
+  if (safeEqual(presented, expectedToken)) return true;
+
+  const parts = presented.split('.');
+  if (parts.length !== 4 || parts[0] !== 'wm1') return false;
+  const expiresAt = Number(parts[2]);
+  if (!Number.isInteger(expiresAt) || expiresAt * 1000 < nowMs) return false;
+  const payload = parts.slice(0, 3).join('.');
+  const signature = createHmac('sha256', expectedToken).update(payload).digest('hex');
+  return safeEqual(parts[3], signature);
+}
+
+const CUSTOM_SECRET_KEY = /^CUSTOM_[A-Z0-9_]{1,64}$/;
+
+// Keys the shell may push through /api/local-env-update: the secret ids it registered at launch
+// (LOCAL_API_SECRET_KEYS) plus custom keys added since. The sidecar's own LOCAL_API_* settings,
+// including the token, can never be changed this way.
+function isUpdatableSecretKey(key, secretKeys) {
+  if (key.startsWith('LOCAL_API_')) return false;
+  return secretKeys.has(key) || CUSTOM_SECRET_KEY.test(key);
+}
+
 const CORS_HEADERS = {
   'Access-Control-Allow-Origin': '*',
   'Access-Control-Allow-Methods': 'GET, POST, PUT, DELETE, OPTIONS',
@@ -299,9 +338,30 @@ async function dispatch(requestUrl, req, routes, context) {
   }
 
   const expectedToken = process.env.LOCAL_API_TOKEN;
+
+  // Unauthenticated on purpose: the desktop shell challenges the listener before it will send
+  // the token anywhere. Only the process that was given the token can produce the proof.
+  // `pidfileProof` is keyed by the token's SHA-256 instead, so a later shell that only has the
+  // hash from the pidfile can still recognise a stale sidecar before killing it.
+  if (requestUrl.pathname === '/api/local-identity') {
+    const nonce = requestUrl.searchParams.get('nonce') || '';
+    if (!/^[0-9a-f]{16,128}$/.test(nonce)) {
+      return json({ error: 'expected hex nonce' }, 400);
+    }
+    if (!expectedToken) {
+      return json({ error: 'identity unavailable without LOCAL_API_TOKEN' }, 404);
+    }
+    const message = `wm-identity.${nonce}.${process.pid}`;
+    const proof = createHmac('sha256', expectedToken).update(message).digest('hex');
+    const tokenHash = createHash('sha256').update(expectedToken).digest('hex');
+    const pidfileProof = createHmac('sha256', tokenHash).update(message).digest('hex');
+    return json({ pid: process.pid, proof, pidfileProof });
+  }
+
+  const authHeader = req.headers.authorization || '';
+  const presented = authHeader.startsWith('Bearer ') ? authHeader.slice('Bearer '.length) : '';
   if (expectedToken) {
-    const authHeader = req.headers.authorization || '';
-    if (authHeader !== `Bearer ${expectedToken}`) {
+    if (!isAuthorizedToken(presented, expectedToken)) {
       context.logger.warn(`[local-api] unauthorized request to ${requestUrl.pathname}`);
       return json({ error: 'Unauthorized' }, 401);
     }
@@ -334,11 +394,21 @@ async function dispatch(requestUrl, req, routes, context) {
     return json({ verboseMode });
   }
   if (requestUrl.pathname === '/api/local-env-update') {
+    // Shell-only control channel: per-window tokens are valid elsewhere but never here, and
+    // without a token there is no shell to talk to.
+    if (!expectedToken || !safeEqual(presented, expectedToken)) {
+      context.logger.warn('[local-api] env update refused: not the shell token');
+      return json({ error: 'Forbidden' }, 403);
+    }
     if (req.method === 'POST') {
       const body = await readBody(req);
       if (body) {
         try {
           const { key, value } = JSON.parse(body.toString());
+          if (typeof key === 'string' && !isUpdatableSecretKey(key, context.secretKeys)) {
+            context.logger.warn(`[local-api] env update refused for ${key.slice(0, 100)}`);
+            return json({ error: `not an updatable secret: ${key.slice(0, 100)}` }, 403);
+          }
           if (typeof key === 'string' && key.length > 0 && key.length < 100) {
             if (value == null || value === '') {
               delete process.env[key];
@@ -435,7 +505,7 @@ export async function createLocalApiServer(options = {}) {
     }
 
     const start = Date.now();
-    const skipRecord = requestUrl.pathname === '/api/local-traffic-log' || requestUrl.pathname === '/api/local-debug-toggle' || requestUrl.pathname === '/api/local-env-update';
+    const skipRecord = requestUrl.pathname === '/api/local-traffic-log' || requestUrl.pathname === '/api/local-debug-toggle' || requestUrl.pathname === '/api/local-env-update' || requestUrl.pathname === '/api/local-identity';
 
     try {
       const response = await dispatch(requestUrl, req, routes, context);
@@ -511,14 +581,39 @@ export async function createLocalApiServer(options = {}) {
     async close() {
       await new Promise((resolve, reject) => {
         server.close((error) => (error ? reject(error) : resolve()));
+        server.closeIdleConnections?.();
       });
     },
   };
 }
 
+const SHUTDOWN_TIMEOUT_MS = 3000;
+
+function installShutdownHandlers(app, logger = console) {
+  let shuttingDown = false;
+  const shutdown = (signal) => {
+    if (shuttingDown) return;
+    shuttingDown = true;
+    logger.log(`[local-api] ${signal} received, shutting down`);
+    saveVerboseState();
+    // The desktop shell escalates to SIGKILL after its own grace period; exit before that.
+    setTimeout(() => process.exit(0), SHUTDOWN_TIMEOUT_MS).unref();
+    app.close().then(
+      () => process.exit(0),
+      (error) => {
+        logger.error('[local-api] shutdown failed', error);
+        process.exit(1);
+      },
+    );
+  };
+  process.once('SIGTERM', () => shutdown('SIGTERM'));
+  process.once('SIGINT', () => shutdown('SIGINT'));
+}
+
 if (isMainModule()) {
   try {
     const app = await createLocalApiServer();
+    installShutdownHandlers(app);
     await app.start();
   } catch (error) {
     console.error('[local-api] startup failed', error);
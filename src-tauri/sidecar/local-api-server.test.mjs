@@ -1,10 +1,13 @@
 import { strict as assert } from 'node:assert';
+import { spawn } from 'node:child_process';
 import { mkdtemp, mkdir, rm, writeFile } from 'node:fs/promises';
+import { createHash, createHmac } from 'node:crypto';
 import { createServer } from 'node:http';
 import os from 'node:os';
 import path from 'node:path';
 import test from 'node:test';
-import { createLocalApiServer } from './local-api-server.mjs';
+import { fileURLToPath } from 'node:url';
+import { createLocalApiServer, isAuthorizedToken } from './local-api-server.mjs';
 
 async function listen(server, host = '127.0.0.1', port = 0) {
   await new Promise((resolve, reject) => {
@@ -359,3 +362,170 @@ test('resolves packaged tauri resource layout under _up_/api', async () => {
     await remote.close();
   }
 });
+
+test('applies env updates only when they carry the sidecar token', async () => {
+  const localApi = await setupApiDir({
+    'secret-echo.js': `
+      export default async function handler() {
+        return new Response(JSON.stringify({ value: process.env.WM_TEST_SECRET ?? null }), {
+          status: 200,
+          headers: { 'content-type': 'application/json' }
+        });
+      }
+    `,
+  });
+  const previousToken = process.env.LOCAL_API_TOKEN;
+  process.env.LOCAL_API_TOKEN = 'test-token';
+
+  const app = await createLocalApiServer({
+    port: 0,
+    apiDir: localApi.apiDir,
+    secretKeys: 'WM_TEST_SECRET',
+    logger: { log() {}, warn() {}, error() {} },
+  });
+  const { port } = await app.start();
+  const authorized = { authorization: 'Bearer test-token' };
+  const update = (headers, value) => fetch(`http://127.0.0.1:${port}/api/local-env-update`, {
+    method: 'POST',
+    headers: { 'content-type': 'application/json', ...headers },
+    body: JSON.stringify({ key: 'WM_TEST_SECRET', value }),
+  });
+  const echo = async () => (await (await fetch(`http://127.0.0.1:${port}/api/secret-echo`, { headers: authorized })).json()).value;
+
+  try {
+    assert.equal((await update({}, 'rejected')).status, 401);
+    assert.equal(await echo(), null);
+
+    assert.equal((await update(authorized, 'accepted')).status, 200);
+    assert.equal(await echo(), 'accepted');
+
+    assert.equal((await update(authorized, null)).status, 200);
+    assert.equal(await echo(), null);
+  } finally {
+    delete process.env.WM_TEST_SECRET;
+    if (previousToken === undefined) delete process.env.LOCAL_API_TOKEN;
+    else process.env.LOCAL_API_TOKEN = previousToken;
+    await app.close();
+    await localApi.cleanup();
+  }
+});
+
+test('env updates accept only the shell token and registered secret keys', async () => {
+  const localApi = await setupApiDir({});
+  const previousToken = process.env.LOCAL_API_TOKEN;
+  const master = 'b'.repeat(64);
+  process.env.LOCAL_API_TOKEN = master;
+
+  const app = await createLocalApiServer({
+    port: 0,
+    apiDir: localApi.apiDir,
+    secretKeys: 'GROQ_API_KEY',
+    logger: { log() {}, warn() {}, error() {} },
+  });
+  const { port } = await app.start();
+  const payload = `wm1.main.${Math.floor(Date.now() / 1000) + 600}`;
+  const windowToken = `${payload}.${createHmac('sha256', master).update(payload).digest('hex')}`;
+  const update = (token, key, value = 'x') => fetch(`http://127.0.0.1:${port}/api/local-env-update`, {
+    method: 'POST',
+    headers: { 'content-type': 'application/json', authorization: `Bearer ${token}` },
+    body: JSON.stringify({ key, value }),
+  });
+
+  try {
+    assert.equal((await update(windowToken, 'GROQ_API_KEY')).status, 403);
+    assert.equal((await update(master, 'LOCAL_API_TOKEN', '')).status, 403);
+    assert.equal(process.env.LOCAL_API_TOKEN, master);
+    assert.equal((await update(master, 'LOCAL_API_CLOUD_FALLBACK', 'true')).status, 403);
+    assert.equal((await update(master, 'PATH')).status, 403);
+    assert.equal((await update(master, 'GROQ_API_KEY')).status, 200);
+    assert.equal((await update(master, 'CUSTOM_INTERNAL_FEED')).status, 200);
+  } finally {
+    delete process.env.GROQ_API_KEY;
+    delete process.env.CUSTOM_INTERNAL_FEED;
+    if (previousToken === undefined) delete process.env.LOCAL_API_TOKEN;
+    else process.env.LOCAL_API_TOKEN = previousToken;
+    await app.close();
+    await localApi.cleanup();
+  }
+});
+
+test('accepts unexpired per-window tokens signed with the sidecar token', () => {
+  const master = 'a'.repeat(64);
+  const now = Date.UTC(2026, 0, 1);
+  const mint = (label, expiresAt, key = master) => {
+    const payload = `wm1.${label}.${expiresAt}`;
+    return `${payload}.${createHmac('sha256', key).update(payload).digest('hex')}`;
+  };
+  const future = now / 1000 + 60;
+
+  assert.equal(isAuthorizedToken(master, master, now), true);
+  assert.equal(isAuthorizedToken(mint('main', future), master, now), true);
+  assert.equal(isAuthorizedToken(mint('main', now / 1000 - 1), master, now), false);
+  assert.equal(isAuthorizedToken(mint('main', future, 'b'.repeat(64)), master, now), false);
+  assert.equal(isAuthorizedToken(mint('main', future).replace('.main.', '.settings.'), master, now), false);
+  assert.equal(isAuthorizedToken('', master, now), false);
+});
+
+test('answers identity challenges with a token-signed proof without requiring auth', async () => {
+  const localApi = await setupApiDir({});
+  const previousToken = process.env.LOCAL_API_TOKEN;
+  process.env.LOCAL_API_TOKEN = 'identity-token';
+
+  const app = await createLocalApiServer({
+    port: 0,
+    apiDir: localApi.apiDir,
+    logger: { log() {}, warn() {}, error() {} },
+  });
+  const { port } = await app.start();
+
+  try {
+    const nonce = '0123456789abcdef0123456789abcdef';
+    const response = await fetch(`http://127.0.0.1:${port}/api/local-identity?nonce=${nonce}`);
+    assert.equal(response.status, 200);
+    const body = await response.json();
+    assert.equal(body.pid, process.pid);
+    const expected = createHmac('sha256', 'identity-token').update(`wm-identity.${nonce}.${process.pid}`).digest('hex');
+    assert.equal(body.proof, expected);
+    const tokenHash = createHash('sha256').update('identity-token').digest('hex');
+    const pidfileExpected = createHmac('sha256', tokenHash).update(`wm-identity.${nonce}.${process.pid}`).digest('hex');
+    assert.equal(body.pidfileProof, pidfileExpected);
+
+    const invalid = await fetch(`http://127.0.0.1:${port}/api/local-identity?nonce=not-hex`);
+    assert.equal(invalid.status, 400);
+  } finally {
+    if (previousToken === undefined) delete process.env.LOCAL_API_TOKEN;
+    else process.env.LOCAL_API_TOKEN = previousToken;
+    await app.close();
+    await localApi.cleanup();
+  }
+});
+
+test('exits cleanly on SIGTERM when run as the desktop sidecar', { skip: process.platform === 'win32' }, async () => {
+  const resourceDir = await mkdtemp(path.join(os.tmpdir(), 'wm-sidecar-signal-test-'));
+  const child = spawn(process.execPath, [fileURLToPath(new URL('./local-api-server.mjs', import.meta.url))], {
+    env: { ...process.env, LOCAL_API_PORT: '0', LOCAL_API_RESOURCE_DIR: resourceDir },
+    stdio: ['ignore', 'pipe', 'pipe'],
+  });
+
+  try {
+    let stdout = '';
+    await new Promise((resolve, reject) => {
+      child.stdout.on('data', (chunk) => {
+        stdout += chunk;
+        if (stdout.includes('listening on')) resolve();
+      });
+      child.once('exit', (code) => reject(new Error(`sidecar exited early with ${code}`)));
+    });
+
+    const exited = new Promise((resolve) => child.once('exit', (code, signal) => resolve({ code, signal })));
+    child.kill('SIGTERM');
+    const { code, signal } = await exited;
+
+    assert.equal(signal, null);
+    assert.equal(code, 0);
+    assert.match(stdout, /SIGTERM received/);
+  } finally {
+    child.kill('SIGKILL');
+    await rm(resourceDir, { recursive: true, force: true });
+  }
+});
@@ -0,0 +1,71 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_QUERY_LIMIT: usize = 200;
+
+/// One line of the secret audit trail. Records who touched which key and whether it worked;
+/// secret values are never part of an event.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    /// Unix milliseconds.
+    pub timestamp: u64,
+    pub action: String,
+    pub key: Option<String>,
+    /// Label of the window that issued the command (`main`, `settings`, ...).
+    pub window: String,
+    pub profile: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Filters for `query`; every field is optional and they combine with AND.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQuery {
+    pub key: Option<String>,
+    pub action: Option<String>,
+    pub window: Option<String>,
+    /// Only events at or after this Unix millisecond timestamp.
+    pub since: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+/// Appends one JSON line. The file is only ever opened for append and is owner-only on Unix.
+pub fn append(path: &Path, event: &AuditEvent) -> Result<(), String> {
+    let line = serde_json::to_string(event).map_err(|e| format!("Failed to serialize audit event: {e}"))?;
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to open audit log {}: {e}", path.display()))?;
+    writeln!(file, "{line}").map_err(|e| format!("Failed to write audit log {}: {e}", path.display()))
+}
+
+/// Matching events, newest first. Lines that don't parse (e.g. a torn final write) are skipped.
+pub fn query(path: &Path, filter: &AuditQuery) -> Result<Vec<AuditEvent>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read audit log {}: {e}", path.display()))?;
+    let limit = filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+    Ok(contents
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<AuditEvent>(line).ok())
+        .filter(|event| filter.key.as_ref().is_none_or(|key| event.key.as_ref() == Some(key)))
+        .filter(|event| filter.action.as_ref().is_none_or(|action| event.action == *action))
+        .filter(|event| filter.window.as_ref().is_none_or(|window| event.window == *window))
+        .filter(|event| filter.since.is_none_or(|since| event.timestamp >= since))
+        .take(limit)
+        .collect())
+}
@@ -0,0 +1,811 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::Serialize;
+use serde_json::Value;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS entries (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+)";
+
+/// Upgrades applied in order on open; `PRAGMA user_version` records how many have run.
+const MIGRATIONS: &[&str] = &[
+    // Expiry, LRU and quota bookkeeping. `size` counts key plus serialized value bytes.
+    "ALTER TABLE entries ADD COLUMN expires_at INTEGER;
+     ALTER TABLE entries ADD COLUMN accessed_at INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE entries ADD COLUMN size INTEGER NOT NULL DEFAULT 0;
+     UPDATE entries SET accessed_at = updated_at,
+         size = length(CAST(key AS BLOB)) + length(CAST(value AS BLOB));
+     CREATE INDEX IF NOT EXISTS entries_accessed_at ON entries (accessed_at);
+     CREATE INDEX IF NOT EXISTS entries_expires_at ON entries (expires_at);",
+];
+
+const DEFAULT_TTL_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+/// How stale `accessed_at` may get before a read refreshes it. Each refresh is a write (and an
+/// fsync), so reads only pay for one when LRU ordering would otherwise drift by more than this.
+const ACCESS_TOUCH_INTERVAL_MS: u64 = 60 * 1000;
+
+/// Limits enforced on every write and by the periodic sweep.
+#[derive(Clone, Copy)]
+pub struct CachePolicy {
+    /// Expiry, counted from `updated_at`, for entries written without their own TTL. It is
+    /// applied when reading and sweeping, so changing it also covers existing entries.
+    /// `None` keeps such entries until evicted.
+    pub default_ttl_ms: Option<u64>,
+    /// Total key + value bytes before least-recently-used entries are evicted.
+    pub max_bytes: u64,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            default_ttl_ms: Some(DEFAULT_TTL_MS),
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+/// One row of `list`; the payload itself is left out.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntryInfo {
+    pub key: String,
+    /// Key plus serialized value, in bytes.
+    pub size: u64,
+    pub updated_at: u64,
+    pub accessed_at: u64,
+    /// When the entry expires, from its own TTL or the policy default; `None` if never.
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub path: PathBuf,
+    pub entries: u64,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub default_ttl_ms: Option<u64>,
+    pub oldest_updated_at: Option<u64>,
+    pub newest_updated_at: Option<u64>,
+    /// Set when this launch had to quarantine a corrupt database.
+    pub recovery: Option<CacheRecovery>,
+}
+
+/// Entries removed by one `sweep`.
+pub struct CacheSweep {
+    pub expired: usize,
+    pub evicted: Vec<String>,
+}
+
+/// What a `cache-changed` notification reports. Expiry isn't reported: an expired entry already
+/// reads as missing everywhere, so removing it changes nothing a window can see.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CacheChangeAction {
+    Write,
+    Delete,
+    Evict,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheChange {
+    pub action: CacheChangeAction,
+    /// The entry written or removed; `None` when a whole prefix went.
+    pub key: Option<String>,
+    /// Set for prefix deletes; an empty prefix means the cache was cleared.
+    pub prefix: Option<String>,
+    /// Unix milliseconds.
+    pub timestamp: u64,
+    /// Label of the window whose command caused the change; `None` for the shell's own sweeps.
+    pub source: Option<String>,
+}
+
+impl CacheChange {
+    /// Whether a subscriber watching keys under `prefix` should hear about this change.
+    pub fn affects(&self, prefix: &str) -> bool {
+        match (&self.key, &self.prefix) {
+            (Some(key), _) => key.starts_with(prefix),
+            (None, Some(deleted)) => deleted.starts_with(prefix) || prefix.starts_with(deleted.as_str()),
+            (None, None) => true,
+        }
+    }
+}
+
+/// Persistent panel cache: one SQLite row per key, so a lookup or write touches only that entry
+/// instead of re-parsing and re-writing every cached payload.
+pub struct CacheStore {
+    path: PathBuf,
+    conn: Mutex<Connection>,
+    policy: CachePolicy,
+    recovery: Option<CacheRecovery>,
+    /// Set by every change since the last backup; starts set so each session can take one.
+    dirty: AtomicBool,
+}
+
+/// What `open` did with a database that failed its integrity check.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheRecovery {
+    pub reason: String,
+    /// Where the damaged file was moved; it is kept for inspection, never deleted.
+    pub quarantined: PathBuf,
+    pub restored_from_backup: bool,
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Moves the database and its WAL/shared-memory siblings aside as `<name>.corrupt-<unix ms>`.
+fn quarantine(path: &Path) -> Result<PathBuf, String> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    let target = with_suffix(path, &format!(".corrupt-{stamp}"));
+    for sidecar in ["-wal", "-shm"] {
+        let from = with_suffix(path, sidecar);
+        if from.exists() {
+            let _ = fs::rename(&from, with_suffix(&target, sidecar));
+        }
+    }
+    fs::rename(path, &target)
+        .map_err(|e| format!("Failed to quarantine cache database {}: {e}", path.display()))?;
+    Ok(target)
+}
+
+/// Deletes least-recently-used entries, except `keep`, until the total size is within
+/// `max_bytes`. Returns the evicted keys.
+fn evict_over_quota(tx: &Transaction<'_>, max_bytes: u64, keep: Option<&str>) -> Result<Vec<String>, String> {
+    let total: i64 = tx
+        .query_row("SELECT COALESCE(SUM(size), 0) FROM entries", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to measure cache size: {e}"))?;
+    let mut excess = total.saturating_sub(i64::try_from(max_bytes).unwrap_or(i64::MAX));
+    if excess <= 0 {
+        return Ok(Vec::new());
+    }
+    let victims = {
+        let mut stmt = tx
+            .prepare("SELECT key, size FROM entries WHERE key IS NOT ?1 ORDER BY accessed_at ASC")
+            .map_err(|e| format!("Failed to plan cache eviction: {e}"))?;
+        let rows = stmt
+            .query_map(params![keep], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+            .map_err(|e| format!("Failed to plan cache eviction: {e}"))?;
+        let mut victims = Vec::new();
+        for row in rows {
+            let (key, size) = row.map_err(|e| format!("Failed to plan cache eviction: {e}"))?;
+            victims.push(key);
+            excess -= size;
+            if excess <= 0 {
+                break;
+            }
+        }
+        victims
+    };
+    for key in &victims {
+        tx.execute("DELETE FROM entries WHERE key = ?1", params![key])
+            .map_err(|e| format!("Failed to evict cache entry {key}: {e}"))?;
+    }
+    Ok(victims)
+}
+
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+impl CacheStore {
+    /// Opens (or creates) the database at `path`. If it is unreadable or fails SQLite's
+    /// integrity check, the file is quarantined and the last-known-good backup is restored;
+    /// failing that the cache starts empty. Either way `recovery()` reports what happened.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create app data directory {}: {e}", parent.display()))?;
+        }
+        let reason = match Self::open_checked(path) {
+            Ok(store) => return Ok(store),
+            Err(reason) => reason,
+        };
+        let quarantined = quarantine(path)?;
+
+        let backup = Self::backup_path_for(path);
+        let mut restored_from_backup = false;
+        if backup.exists() && fs::copy(&backup, path).is_ok() {
+            restored_from_backup = Self::open_checked(path).is_ok();
+            if !restored_from_backup {
+                quarantine(path)?;
+            }
+        }
+        let mut store = Self::open_checked(path)?;
+        store.recovery = Some(CacheRecovery {
+            reason,
+            quarantined,
+            restored_from_backup,
+        });
+        Ok(store)
+    }
+
+    fn open_checked(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open cache database {}: {e}", path.display()))?;
+        let store = Self::init(path.to_path_buf(), conn)?;
+        let verdict: String = store
+            .conn()?
+            .query_row("PRAGMA quick_check", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to check cache database {}: {e}", path.display()))?;
+        if verdict != "ok" {
+            return Err(format!("Cache database {} failed integrity check: {verdict}", path.display()));
+        }
+        Ok(store)
+    }
+
+    /// A throwaway in-memory store, used when the database file can't be opened so panels
+    /// still cache for the session.
+    pub fn in_memory() -> Result<Self, String> {
+        let conn = Connection::open_in_memory().map_err(|e| format!("Failed to open in-memory cache: {e}"))?;
+        Self::init(PathBuf::from(":memory:"), conn)
+    }
+
+    /// WAL with `synchronous = FULL`: every commit is appended to the log and fsynced before it
+    /// returns, so a crash or power cut loses at most the write in flight, never earlier entries.
+    fn init(path: PathBuf, conn: Connection) -> Result<Self, String> {
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .and_then(|_| conn.pragma_update(None, "synchronous", "FULL"))
+            .map_err(|e| format!("Failed to configure cache database {}: {e}", path.display()))?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to create cache schema in {}: {e}", path.display()))?;
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| format!("Failed to read cache schema version in {}: {e}", path.display()))?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            conn.execute_batch(&format!("BEGIN; {migration} PRAGMA user_version = {}; COMMIT;", index + 1))
+                .map_err(|e| format!("Failed to upgrade cache schema in {}: {e}", path.display()))?;
+        }
+        Ok(Self {
+            path,
+            conn: Mutex::new(conn),
+            policy: CachePolicy::default(),
+            recovery: None,
+            dirty: AtomicBool::new(true),
+        })
+    }
+
+    pub fn set_policy(&mut self, policy: CachePolicy) {
+        self.policy = policy;
+    }
+
+    /// Entries without their own TTL that were last written at or before this are stale.
+    fn stale_before(&self, now: u64) -> Option<i64> {
+        self.policy.default_ttl_ms.map(|ttl| now.saturating_sub(ttl) as i64)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn recovery(&self) -> Option<&CacheRecovery> {
+        self.recovery.as_ref()
+    }
+
+    fn backup_path_for(path: &Path) -> PathBuf {
+        with_suffix(path, ".bak")
+    }
+
+    fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    /// Takes a `backup` if the cache changed since the last one and the existing backup is at
+    /// least `min_interval` old. Returns the backup path when one was written; in-memory stores
+    /// are never backed up.
+    pub fn backup_if_due(&self, min_interval: Duration) -> Result<Option<PathBuf>, String> {
+        if self.path == Path::new(":memory:") || !self.dirty.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let age = fs::metadata(Self::backup_path_for(&self.path))
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        if age.is_some_and(|age| age < min_interval) {
+            return Ok(None);
+        }
+        // Cleared first so a write racing the snapshot marks the cache dirty again.
+        self.dirty.store(false, Ordering::SeqCst);
+        match self.backup() {
+            Ok(path) => Ok(Some(path)),
+            Err(err) => {
+                self.mark_dirty();
+                Err(err)
+            }
+        }
+    }
+
+    /// Snapshots the database to `<name>.bak` as the last-known-good copy. The snapshot is
+    /// written to a temp file, fsynced, then renamed over the old backup, so a crash mid-backup
+    /// leaves the previous one intact.
+    pub fn backup(&self) -> Result<PathBuf, String> {
+        let backup = Self::backup_path_for(&self.path);
+        let tmp = with_suffix(&backup, ".tmp");
+        if tmp.exists() {
+            fs::remove_file(&tmp)
+                .map_err(|e| format!("Failed to remove stale cache backup {}: {e}", tmp.display()))?;
+        }
+        self.conn()?
+            .execute("VACUUM INTO ?1", params![tmp.to_string_lossy()])
+            .map_err(|e| format!("Failed to snapshot cache database to {}: {e}", tmp.display()))?;
+        File::open(&tmp)
+            .and_then(|file| file.sync_all())
+            .map_err(|e| format!("Failed to flush cache backup {}: {e}", tmp.display()))?;
+        fs::rename(&tmp, &backup)
+            .map_err(|e| format!("Failed to replace cache backup {}: {e}", backup.display()))?;
+        if let Some(parent) = backup.parent() {
+            sync_dir(parent);
+        }
+        Ok(backup)
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|_| "Failed to lock cache database".to_string())
+    }
+
+    /// Returns the entry unless it has expired (expired entries are dropped on sight) and marks
+    /// it as used for LRU eviction, at most once per `ACCESS_TOUCH_INTERVAL_MS`.
+    pub fn get(&self, key: &str, now: u64) -> Result<Option<Value>, String> {
+        let conn = self.conn()?;
+        let row: Option<(String, i64, Option<i64>, i64)> = conn
+            .query_row(
+                "SELECT value, updated_at, expires_at, accessed_at FROM entries WHERE key = ?1",
+                params![key],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read cache entry {key}: {e}"))?;
+        let Some((raw, updated_at, expires_at, accessed_at)) = row else {
+            return Ok(None);
+        };
+        let expired = match expires_at {
+            Some(expires_at) => expires_at <= now as i64,
+            None => self.stale_before(now).is_some_and(|cutoff| updated_at <= cutoff),
+        };
+        if expired {
+            conn.execute("DELETE FROM entries WHERE key = ?1", params![key])
+                .map_err(|e| format!("Failed to drop expired cache entry {key}: {e}"))?;
+            return Ok(None);
+        }
+        if now.saturating_sub(accessed_at.max(0) as u64) >= ACCESS_TOUCH_INTERVAL_MS {
+            conn.execute("UPDATE entries SET accessed_at = ?2 WHERE key = ?1", params![key, now as i64])
+                .map_err(|e| format!("Failed to touch cache entry {key}: {e}"))?;
+        }
+        serde_json::from_str(&raw)
+            .map(Some)
+            .map_err(|e| format!("Cache entry {key} is corrupt: {e}"))
+    }
+
+    /// Writes the entry, expiring after `ttl_ms` if given (otherwise the policy default applies),
+    /// and in the same transaction evicts least-recently-used entries until the cache fits its
+    /// quota again. The entry just written is never evicted, even if it alone exceeds the quota.
+    /// Returns the evicted keys.
+    pub fn set(&self, key: &str, value: &Value, now: u64, ttl_ms: Option<u64>) -> Result<Vec<String>, String> {
+        let raw = serde_json::to_string(value).map_err(|e| format!("Failed to serialize cache entry {key}: {e}"))?;
+        let expires_at = ttl_ms.map(|ttl| i64::try_from(now.saturating_add(ttl)).unwrap_or(i64::MAX));
+        let size = (key.len() + raw.len()) as i64;
+        let mut conn = self.conn()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start cache write for {key}: {e}"))?;
+        tx.execute(
+            "INSERT INTO entries (key, value, updated_at, expires_at, accessed_at, size)
+             VALUES (?1, ?2, ?3, ?4, ?3, ?5)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at,
+                 expires_at = excluded.expires_at, accessed_at = excluded.accessed_at, size = excluded.size",
+            params![key, raw, now as i64, expires_at, size],
+        )
+        .map_err(|e| format!("Failed to write cache entry {key}: {e}"))?;
+        let evicted = evict_over_quota(&tx, self.policy.max_bytes, Some(key))?;
+        tx.commit().map_err(|e| format!("Failed to commit cache entry {key}: {e}"))?;
+        self.mark_dirty();
+        Ok(evicted)
+    }
+
+    /// Entries ordered by key, optionally only those starting with `prefix`.
+    pub fn list(&self, prefix: Option<&str>) -> Result<Vec<CacheEntryInfo>, String> {
+        let default_ttl = self.policy.default_ttl_ms.map(|ttl| ttl as i64);
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT key, size, updated_at, accessed_at, COALESCE(expires_at, updated_at + ?2) FROM entries
+                 WHERE ?1 IS NULL OR substr(key, 1, length(?1)) = ?1 ORDER BY key",
+            )
+            .map_err(|e| format!("Failed to list cache entries: {e}"))?;
+        let rows = stmt
+            .query_map(params![prefix, default_ttl], |row| {
+                Ok(CacheEntryInfo {
+                    key: row.get(0)?,
+                    size: row.get::<_, i64>(1)? as u64,
+                    updated_at: row.get::<_, i64>(2)? as u64,
+                    accessed_at: row.get::<_, i64>(3)? as u64,
+                    expires_at: row.get::<_, Option<i64>>(4)?.map(|at| at as u64),
+                })
+            })
+            .map_err(|e| format!("Failed to list cache entries: {e}"))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to list cache entries: {e}"))
+    }
+
+    /// Returns whether the key existed.
+    pub fn delete(&self, key: &str) -> Result<bool, String> {
+        let deleted = self
+            .conn()?
+            .execute("DELETE FROM entries WHERE key = ?1", params![key])
+            .map_err(|e| format!("Failed to delete cache entry {key}: {e}"))?;
+        if deleted > 0 {
+            self.mark_dirty();
+        }
+        Ok(deleted > 0)
+    }
+
+    /// Deletes every key starting with `prefix` and returns how many went. An empty prefix is
+    /// refused so a blank field can't wipe the cache; use `clear` for that.
+    pub fn delete_prefix(&self, prefix: &str) -> Result<usize, String> {
+        if prefix.is_empty() {
+            return Err("Cache key prefix is empty".to_string());
+        }
+        let deleted = self
+            .conn()?
+            .execute("DELETE FROM entries WHERE substr(key, 1, length(?1)) = ?1", params![prefix])
+            .map_err(|e| format!("Failed to delete cache entries under {prefix}: {e}"))?;
+        if deleted > 0 {
+            self.mark_dirty();
+        }
+        Ok(deleted)
+    }
+
+    /// Removes every entry and returns how many there were.
+    pub fn clear(&self) -> Result<usize, String> {
+        let deleted = self
+            .conn()?
+            .execute("DELETE FROM entries", [])
+            .map_err(|e| format!("Failed to clear cache: {e}"))?;
+        if deleted > 0 {
+            self.mark_dirty();
+        }
+        Ok(deleted)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats, String> {
+        let (entries, total_bytes, oldest, newest): (i64, i64, Option<i64>, Option<i64>) = self
+            .conn()?
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(size), 0), MIN(updated_at), MAX(updated_at) FROM entries",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .map_err(|e| format!("Failed to read cache stats: {e}"))?;
+        Ok(CacheStats {
+            path: self.path.clone(),
+            entries: entries as u64,
+            total_bytes: total_bytes as u64,
+            max_bytes: self.policy.max_bytes,
+            default_ttl_ms: self.policy.default_ttl_ms,
+            oldest_updated_at: oldest.map(|at| at as u64),
+            newest_updated_at: newest.map(|at| at as u64),
+            recovery: self.recovery.clone(),
+        })
+    }
+
+    /// Drops expired entries, then evicts least-recently-used ones until under quota.
+    pub fn sweep(&self, now: u64) -> Result<CacheSweep, String> {
+        let mut conn = self.conn()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start cache sweep: {e}"))?;
+        let expired = tx
+            .execute(
+                "DELETE FROM entries WHERE expires_at <= ?1 OR (expires_at IS NULL AND updated_at <= ?2)",
+                params![now as i64, self.stale_before(now)],
+            )
+            .map_err(|e| format!("Failed to drop expired cache entries: {e}"))?;
+        let evicted = evict_over_quota(&tx, self.policy.max_bytes, None)?;
+        tx.commit().map_err(|e| format!("Failed to commit cache sweep: {e}"))?;
+        if expired + evicted.len() > 0 {
+            self.mark_dirty();
+        }
+        Ok(CacheSweep { expired, evicted })
+    }
+
+    /// One-time import of the legacy `persistent-cache.json` object. Every key is inserted in a
+    /// single transaction, then the file is renamed to `<name>.migrated` so the import never
+    /// repeats. Keys already in the database win over the legacy copy. Returns the number of
+    /// entries imported, or `None` when there was nothing to migrate.
+    pub fn migrate_json(&self, legacy: &Path, updated_at: u64) -> Result<Option<usize>, String> {
+        if !legacy.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(legacy)
+            .map_err(|e| format!("Failed to read legacy cache {}: {e}", legacy.display()))?;
+        let root = match serde_json::from_str::<Value>(&contents) {
+            Ok(Value::Object(root)) => root,
+            Ok(_) => return Err(Self::quarantine_legacy(legacy, "is not a JSON object".to_string())),
+            Err(e) => return Err(Self::quarantine_legacy(legacy, format!("is corrupt: {e}"))),
+        };
+
+        let mut conn = self.conn()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start cache migration: {e}"))?;
+        let mut imported = 0;
+        for (key, value) in &root {
+            let raw = serde_json::to_string(value)
+                .map_err(|e| format!("Failed to serialize cache entry {key}: {e}"))?;
+            let entry_updated_at = value
+                .get("updatedAt")
+                .and_then(Value::as_u64)
+                .unwrap_or(updated_at);
+            imported += tx
+                .execute(
+                    "INSERT OR IGNORE INTO entries (key, value, updated_at, accessed_at, size)
+                     VALUES (?1, ?2, ?3, ?3, ?4)",
+                    params![key, raw, entry_updated_at as i64, (key.len() + raw.len()) as i64],
+                )
+                .map_err(|e| format!("Failed to migrate cache entry {key}: {e}"))?;
+        }
+        tx.commit().map_err(|e| format!("Failed to commit cache migration: {e}"))?;
+        drop(conn);
+        if imported > 0 {
+            self.mark_dirty();
+        }
+
+        fs::rename(legacy, with_suffix(legacy, ".migrated"))
+            .map_err(|e| format!("Failed to retire legacy cache {}: {e}", legacy.display()))?;
+        Ok(Some(imported))
+    }
+
+    /// Moves an unparseable legacy cache aside so it is neither retried every launch nor lost.
+    fn quarantine_legacy(legacy: &Path, problem: String) -> String {
+        let target = with_suffix(legacy, ".corrupt");
+        match fs::rename(legacy, &target) {
+            Ok(()) => format!("Legacy cache {} {problem}; moved to {}", legacy.display(), target.display()),
+            Err(e) => format!("Legacy cache {} {problem}; could not move it aside: {e}", legacy.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wm-cache-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn store_with_policy(default_ttl_ms: Option<u64>, max_bytes: u64) -> CacheStore {
+        let mut store = CacheStore::in_memory().unwrap();
+        store.set_policy(CachePolicy { default_ttl_ms, max_bytes });
+        store
+    }
+
+    fn keys(store: &CacheStore) -> Vec<String> {
+        store.list(None).unwrap().into_iter().map(|entry| entry.key).collect()
+    }
+
+    #[test]
+    fn migrations_upgrade_the_original_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn.execute(
+            "INSERT INTO entries (key, value, updated_at) VALUES ('news:feed', '{\"n\":1}', 42)",
+            [],
+        )
+        .unwrap();
+
+        let store = CacheStore::init(PathBuf::from(":memory:"), conn).unwrap();
+        let version: usize = store
+            .conn()
+            .unwrap()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        let entries = store.list(None).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].accessed_at, 42);
+        assert_eq!(entries[0].size, ("news:feed".len() + "{\"n\":1}".len()) as u64);
+        assert_eq!(store.get("news:feed", 43).unwrap(), Some(json!({ "n": 1 })));
+    }
+
+    #[test]
+    fn migrate_json_imports_once_and_keeps_existing_keys() {
+        let dir = temp_dir("migrate");
+        let legacy = dir.join("persistent-cache.json");
+        fs::write(&legacy, r#"{"a":{"updatedAt":5,"data":1},"b":{"data":"legacy"}}"#).unwrap();
+        let store = store_with_policy(None, DEFAULT_MAX_BYTES);
+        store.set("b", &json!({ "data": "current" }), 10, None).unwrap();
+
+        assert_eq!(store.migrate_json(&legacy, 7).unwrap(), Some(1));
+        assert!(!legacy.exists());
+        assert!(with_suffix(&legacy, ".migrated").exists());
+        assert_eq!(store.get("a", 20).unwrap(), Some(json!({ "updatedAt": 5, "data": 1 })));
+        assert_eq!(store.list(Some("a")).unwrap()[0].updated_at, 5);
+        assert_eq!(store.get("b", 20).unwrap(), Some(json!({ "data": "current" })));
+        assert_eq!(store.migrate_json(&legacy, 7).unwrap(), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn migrate_json_quarantines_a_corrupt_file() {
+        let dir = temp_dir("migrate-corrupt");
+        let legacy = dir.join("persistent-cache.json");
+        fs::write(&legacy, "{not json").unwrap();
+        let store = CacheStore::in_memory().unwrap();
+
+        assert!(store.migrate_json(&legacy, 7).is_err());
+        assert!(!legacy.exists());
+        assert!(with_suffix(&legacy, ".corrupt").exists());
+        assert!(keys(&store).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Closes `store` and overwrites its database with bytes SQLite can't read.
+    fn corrupt(store: CacheStore) -> PathBuf {
+        let path = store.path().to_path_buf();
+        drop(store);
+        for sidecar in ["-wal", "-shm"] {
+            let _ = fs::remove_file(with_suffix(&path, sidecar));
+        }
+        fs::write(&path, vec![0x5a; 8192]).unwrap();
+        path
+    }
+
+    #[test]
+    fn open_restores_the_backup_of_a_corrupt_database() {
+        let dir = temp_dir("recover");
+        let store = CacheStore::open(&dir.join("cache.sqlite")).unwrap();
+        store.set("news:feed", &json!({ "n": 1 }), 1, None).unwrap();
+        store.set("markets:spx", &json!(4_500), 2, None).unwrap();
+        store.backup().unwrap();
+        store.set("after-backup", &json!(true), 3, None).unwrap();
+        let path = corrupt(store);
+
+        let reopened = CacheStore::open(&path).unwrap();
+        let recovery = reopened.recovery().expect("recovery should be reported");
+        assert!(recovery.restored_from_backup);
+        assert!(recovery.quarantined.exists());
+        assert_eq!(fs::read(&recovery.quarantined).unwrap(), vec![0x5a; 8192]);
+        assert_eq!(keys(&reopened), ["markets:spx", "news:feed"]);
+        assert_eq!(reopened.get("news:feed", 4).unwrap(), Some(json!({ "n": 1 })));
+        assert!(reopened.stats().unwrap().recovery.is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn open_starts_empty_when_there_is_no_backup() {
+        let dir = temp_dir("recover-empty");
+        let store = CacheStore::open(&dir.join("cache.sqlite")).unwrap();
+        store.set("news:feed", &json!(1), 1, None).unwrap();
+        let path = corrupt(store);
+
+        let reopened = CacheStore::open(&path).unwrap();
+        let recovery = reopened.recovery().expect("recovery should be reported");
+        assert!(!recovery.restored_from_backup);
+        assert!(recovery.quarantined.exists());
+        assert!(keys(&reopened).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backups_are_taken_only_after_changes_and_when_due() {
+        let dir = temp_dir("backup");
+        let store = CacheStore::open(&dir.join("cache.sqlite")).unwrap();
+        let backup = CacheStore::backup_path_for(store.path());
+
+        assert_eq!(store.backup_if_due(Duration::ZERO).unwrap(), Some(backup.clone()));
+        assert!(backup.exists());
+        assert_eq!(store.backup_if_due(Duration::ZERO).unwrap(), None);
+        store.set("a", &json!(1), 1, None).unwrap();
+        assert_eq!(store.backup_if_due(Duration::from_secs(3600)).unwrap(), None);
+        assert_eq!(store.backup_if_due(Duration::ZERO).unwrap(), Some(backup));
+        assert_eq!(CacheStore::in_memory().unwrap().backup_if_due(Duration::ZERO).unwrap(), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn get_drops_entries_past_their_ttl() {
+        let store = store_with_policy(Some(1_000), DEFAULT_MAX_BYTES);
+        store.set("own-ttl", &json!(1), 100, Some(50)).unwrap();
+        store.set("default-ttl", &json!(2), 100, None).unwrap();
+
+        assert_eq!(store.get("own-ttl", 149).unwrap(), Some(json!(1)));
+        assert_eq!(store.get("own-ttl", 150).unwrap(), None);
+        assert_eq!(store.get("default-ttl", 1_099).unwrap(), Some(json!(2)));
+        assert_eq!(store.get("default-ttl", 1_100).unwrap(), None);
+        assert!(keys(&store).is_empty());
+    }
+
+    #[test]
+    fn sweep_removes_expired_entries() {
+        let store = store_with_policy(Some(1_000), DEFAULT_MAX_BYTES);
+        store.set("short", &json!(1), 100, Some(50)).unwrap();
+        store.set("stale", &json!(2), 100, None).unwrap();
+        store.set("fresh", &json!(3), 900, None).unwrap();
+        store.set("long", &json!(4), 100, Some(10_000)).unwrap();
+
+        let sweep = store.sweep(1_100).unwrap();
+        assert_eq!(sweep.expired, 2);
+        assert!(sweep.evicted.is_empty());
+        assert_eq!(keys(&store), ["fresh", "long"]);
+    }
+
+    #[test]
+    fn writes_evict_least_recently_used_entries_over_quota() {
+        // Each entry is a one-byte key plus a 10-byte value.
+        let value = json!("12345678");
+        let store = store_with_policy(None, 33);
+        store.set("a", &value, 1, None).unwrap();
+        store.set("b", &value, 2, None).unwrap();
+        store.set("c", &value, 3, None).unwrap();
+        let later = 1 + ACCESS_TOUCH_INTERVAL_MS;
+        store.get("a", later).unwrap();
+
+        assert_eq!(store.set("d", &value, later + 1, None).unwrap(), ["b"]);
+        assert_eq!(keys(&store), ["a", "c", "d"]);
+    }
+
+    #[test]
+    fn reads_touch_accessed_at_at_most_once_per_interval() {
+        let store = store_with_policy(None, DEFAULT_MAX_BYTES);
+        store.set("a", &json!(1), 1_000, None).unwrap();
+        let accessed_at = |store: &CacheStore| store.list(None).unwrap()[0].accessed_at;
+
+        store.get("a", 1_000 + ACCESS_TOUCH_INTERVAL_MS - 1).unwrap();
+        assert_eq!(accessed_at(&store), 1_000);
+        store.get("a", 1_000 + ACCESS_TOUCH_INTERVAL_MS).unwrap();
+        assert_eq!(accessed_at(&store), 1_000 + ACCESS_TOUCH_INTERVAL_MS);
+    }
+
+    #[test]
+    fn huge_ttls_clamp_instead_of_expiring_at_once() {
+        let store = store_with_policy(None, DEFAULT_MAX_BYTES);
+        store.set("forever", &json!(1), 1_000, Some(u64::MAX)).unwrap();
+        store.set("past-i64", &json!(2), 1_000, Some(i64::MAX as u64)).unwrap();
+
+        assert_eq!(store.get("forever", 2_000).unwrap(), Some(json!(1)));
+        assert_eq!(store.get("past-i64", 2_000).unwrap(), Some(json!(2)));
+        assert_eq!(store.list(Some("forever")).unwrap()[0].expires_at, Some(i64::MAX as u64));
+        assert_eq!(store.sweep(2_000).unwrap().expired, 0);
+    }
+
+    #[test]
+    fn eviction_never_removes_the_entry_being_written() {
+        let store = store_with_policy(None, 20);
+        store.set("a", &json!("small"), 1, None).unwrap();
+        let oversized = json!("x".repeat(64));
+
+        assert_eq!(store.set("big", &oversized, 2, None).unwrap(), ["a"]);
+        assert_eq!(keys(&store), ["big"]);
+        assert_eq!(store.get("big", 3).unwrap(), Some(oversized));
+
+        // Without a key to protect, the sweep brings the cache back under quota.
+        assert_eq!(store.sweep(4).unwrap().evicted, ["big"]);
+        assert!(keys(&store).is_empty());
+    }
+}
@@ -1,347 +1,1793 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::fs::{self, File, OpenOptions};
-use std::io::Write;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener};
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::env;
 
+mod audit;
+mod cache;
+mod profiles;
+mod secrets;
+mod vault;
+
+use audit::{AuditEvent, AuditQuery};
+use cache::{CacheChange, CacheChangeAction, CacheEntryInfo, CachePolicy, CacheStats, CacheStore};
+use hmac::{Hmac, Mac};
 use keyring::Entry;
-use serde_json::{Map, Value};
+use profiles::ProfileManifest;
+use secrets::{CustomSecret, ResolvedSecret, SecretDescriptor, SecretKind, SecretSource, SecretStatus, SecretVerdict};
+use vault::FileVault;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use tauri::menu::{AboutMetadata, Menu, MenuItem, PredefinedMenuItem, Submenu};
-use tauri::{AppHandle, Manager, RunEvent, WebviewUrl, WebviewWindowBuilder};
+use tauri::utils::config::Csp;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, RunEvent, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+use tauri_plugin_dialog::DialogExt;
 
-const LOCAL_API_PORT: &str = "46123";
+/// Port baked into the bundled CSP; rewritten at launch to the port actually in use.
+const DEFAULT_LOCAL_API_PORT: u16 = 46123;
 const KEYRING_SERVICE: &str = "world-monitor";
 const LOCAL_API_LOG_FILE: &str = "local-api.log";
+const LOCAL_API_PID_FILE_PREFIX: &str = "local-api-";
+const LOCAL_API_PID_FILE_SUFFIX: &str = ".pid";
+const SECRET_VAULT_FILE: &str = "secrets.vault";
+const SECRET_PROFILES_FILE: &str = "secret-profiles.json";
+const SECRET_DOTENV_FILE: &str = ".env";
+const CUSTOM_SECRETS_FILE: &str = "custom-secrets.json";
+const SECRET_BUNDLE_FORMAT: &str = "world-monitor-secrets";
+const SECRET_BUNDLE_EXTENSION: &str = "wmbundle";
 const DESKTOP_LOG_FILE: &str = "desktop.log";
+const SECRET_AUDIT_LOG_FILE: &str = "secret-audit.log";
+const CACHE_DB_FILE: &str = "persistent-cache.sqlite";
+const LEGACY_CACHE_FILE: &str = "persistent-cache.json";
+const CACHE_SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const CACHE_BACKUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const CACHE_CHANGED_EVENT: &str = "cache-changed";
 const MENU_FILE_SETTINGS_ID: &str = "file.settings";
 const MENU_HELP_GITHUB_ID: &str = "help.github";
 const MENU_HELP_DEVTOOLS_ID: &str = "help.devtools";
-const SUPPORTED_SECRET_KEYS: [&str; 15] = [
-    "GROQ_API_KEY",
-    "OPENROUTER_API_KEY",
-    "FRED_API_KEY",
-    "EIA_API_KEY",
-    "CLOUDFLARE_API_TOKEN",
-    "ACLED_ACCESS_TOKEN",
-    "WINGBITS_API_KEY",
-    "WS_RELAY_URL",
-    "VITE_OPENSKY_RELAY_URL",
-    "OPENSKY_CLIENT_ID",
-    "OPENSKY_CLIENT_SECRET",
-    "AISSTREAM_API_KEY",
-    "VITE_WS_RELAY_URL",
-    "FINNHUB_API_KEY",
-    "NASA_FIRMS_API_KEY",
+const LOCAL_API_STATUS_EVENT: &str = "local-api-status";
+const LOCAL_API_HEALTH_INTERVAL: Duration = Duration::from_secs(5);
+const LOCAL_API_READY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const LOCAL_API_HEALTH_TIMEOUT: Duration = Duration::from_secs(3);
+const LOCAL_API_STARTUP_GRACE: Duration = Duration::from_secs(20);
+const LOCAL_API_MAX_HEALTH_FAILURES: u32 = 3;
+const LOCAL_API_RESTART_BACKOFF_INITIAL: Duration = Duration::from_secs(1);
+const LOCAL_API_RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+const LOCAL_API_STABLE_UPTIME: Duration = Duration::from_secs(120);
+const LOCAL_API_CRASH_LOOP_WINDOW: Duration = Duration::from_secs(600);
+const LOCAL_API_CRASH_LOOP_LIMIT: usize = 5;
+const LOCAL_API_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+const LOCAL_API_GROUP_GRACE: Duration = Duration::from_secs(2);
+const LOCAL_API_WINDOW_TOKEN_TTL: Duration = Duration::from_secs(15 * 60);
+const MIN_NODE_MAJOR_VERSION: u32 = 18;
+const NODE_VERSION_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Inherited variables the sidecar keeps under the default (minimal) environment policy.
+const LOCAL_API_ENV_ALLOWLIST: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "LANG",
+    "LANGUAGE",
+    "LC_*",
+    "TZ",
+    "TMPDIR",
+    "TMP",
+    "TEMP",
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "NO_PROXY",
+    "ALL_PROXY",
+    "http_proxy",
+    "https_proxy",
+    "no_proxy",
+    "all_proxy",
+    "SSL_CERT_FILE",
+    "SSL_CERT_DIR",
+    "NODE_EXTRA_CA_CERTS",
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+    "LOCAL_API_REMOTE_BASE",
+    "LOCAL_API_CLOUD_FALLBACK",
 ];
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum LocalApiPhase {
+    #[default]
+    NotStarted,
+    Starting,
+    Ready,
+    Exited,
+    RestartScheduled,
+    NodeMissing,
+    Failed,
+    Stopped,
+}
+
+/// Snapshot returned by `get_local_api_status` and emitted as the `local-api-status` event.
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LocalApiStatus {
+    phase: LocalApiPhase,
+    pid: Option<u32>,
+    port: u16,
+    uptime_ms: Option<u64>,
+    restart_count: u32,
+    exit_code: Option<i32>,
+    restart_delay_ms: Option<u64>,
+    last_error: Option<String>,
+    node_version: Option<String>,
+    node_candidates: Vec<NodeCandidate>,
+}
+
+/// Per-prefix cache subscriptions, keyed by the event name each one is delivered on.
+#[derive(Default)]
+struct CacheSubscriptions {
+    next_id: AtomicU64,
+    entries: Mutex<HashMap<String, CacheSubscription>>,
+}
+
+struct CacheSubscription {
+    /// Window that subscribed; events go only to it, and the subscription is dropped when it is destroyed.
+    window: String,
+    prefixes: Vec<String>,
+}
+
+/// Returned by `subscribe_cache_changes`: the event to listen on, and the window it is sent to.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheSubscriptionTicket {
+    event: String,
+    window: String,
+}
 
 #[derive(Default)]
 struct LocalApiState {
+    port: u16,
     child: Mutex<Option<Child>>,
     token: Mutex<Option<String>>,
+    started_at: Mutex<Option<Instant>>,
+    status: Mutex<LocalApiStatus>,
+    shutting_down: AtomicBool,
+    identity_verified: AtomicBool,
+    /// Secrets changed while the current sidecar was still starting; pushed once its identity is
+    /// verified. Guards `identity_verified` transitions so a change can't slip between the two.
+    pending_secret_sync: Mutex<BTreeSet<String>>,
+    window_tokens: bool,
 }
 
-fn secret_entry(key: &str) -> Result<Entry, String> {
-    if !SUPPORTED_SECRET_KEYS.contains(&key) {
-        return Err(format!("Unsupported secret key: {key}"));
-    }
-    Entry::new(KEYRING_SERVICE, key).map_err(|e| format!("Keyring init failed: {e}"))
-}
-
-fn generate_local_token() -> String {
-    use std::collections::hash_map::RandomState;
-    use std::hash::{BuildHasher, Hasher};
-    let state = RandomState::new();
-    let mut h1 = state.build_hasher();
-    h1.write_u64(std::process::id() as u64);
-    let a = h1.finish();
-    let mut h2 = state.build_hasher();
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_nanos())
-        .unwrap_or(0);
-    h2.write_u128(nanos);
-    let b = h2.finish();
-    format!("{a:016x}{b:016x}")
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum SecretBackend {
+    Keyring,
+    File,
 }
 
-#[tauri::command]
-fn get_local_api_token(state: tauri::State<'_, LocalApiState>) -> Result<String, String> {
-    let token = state
-        .token
-        .lock()
-        .map_err(|_| "Failed to lock local API token".to_string())?;
-    token.clone().ok_or_else(|| "Token not generated".to_string())
+struct SecretStore {
+    backend: SecretBackend,
+    reason: String,
+    vault: Mutex<FileVault>,
+    /// When set, `get_secret` refuses to hand plaintext to the webview; only the shell
+    /// reads stored values (to inject them into the sidecar or run validation probes).
+    write_only: bool,
+    /// Keyring values already read this session. Some keychains prompt on every access,
+    /// so each entry is fetched at most once and kept in step with writes.
+    keyring_cache: Mutex<HashMap<String, Option<String>>>,
+    /// Named credential sets; the active one namespaces keyring entries and vault keys.
+    profiles: Mutex<ProfileManifest>,
+    profiles_path: PathBuf,
+    /// Bundle decrypted by `preview_secrets_bundle`, waiting for the user to confirm the import.
+    pending_import: Mutex<Option<SecretBundle>>,
+    /// Where secrets are looked up, highest precedence first.
+    layers: Vec<SecretLayer>,
+    dotenv_path: PathBuf,
+    /// Manifest of user-defined `CUSTOM_` keys, re-registered at every launch.
+    custom_manifest_path: PathBuf,
 }
 
-#[tauri::command]
-fn list_supported_secret_keys() -> Vec<String> {
-    SUPPORTED_SECRET_KEYS.iter().map(|key| (*key).to_string()).collect()
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum SecretLayer {
+    Env,
+    Dotenv,
+    /// The keyring or file vault, whichever backend is active.
+    Store,
 }
 
-#[tauri::command]
-fn get_secret(key: String) -> Result<Option<String>, String> {
-    let entry = secret_entry(&key)?;
-    match entry.get_password() {
-        Ok(value) => Ok(Some(value)),
-        Err(keyring::Error::NoEntry) => Ok(None),
-        Err(err) => Err(format!("Failed to read keyring secret: {err}")),
+impl SecretLayer {
+    fn as_str(self) -> &'static str {
+        match self {
+            SecretLayer::Env => "env",
+            SecretLayer::Dotenv => "dotenv",
+            SecretLayer::Store => "store",
+        }
     }
 }
 
-#[tauri::command]
-fn set_secret(key: String, value: String) -> Result<(), String> {
-    let entry = secret_entry(&key)?;
-    entry
-        .set_password(&value)
-        .map_err(|e| format!("Failed to write keyring secret: {e}"))
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretSourceEntry {
+    key: String,
+    /// The source whose value the sidecar receives.
+    effective: Option<SecretSource>,
+    /// Every source that holds a non-empty value, in precedence order.
+    available: Vec<SecretSource>,
+    error: Option<String>,
 }
 
-#[tauri::command]
-fn delete_secret(key: String) -> Result<(), String> {
-    let entry = secret_entry(&key)?;
-    match entry.delete_credential() {
-        Ok(_) => Ok(()),
-        Err(keyring::Error::NoEntry) => Ok(()),
-        Err(err) => Err(format!("Failed to delete keyring secret: {err}")),
-    }
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretSourceReport {
+    precedence: Vec<SecretLayer>,
+    dotenv_path: String,
+    dotenv_exists: bool,
+    keys: Vec<SecretSourceEntry>,
 }
 
-fn cache_file_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?;
-    std::fs::create_dir_all(&dir)
-        .map_err(|e| format!("Failed to create app data directory {}: {e}", dir.display()))?;
-    Ok(dir.join("persistent-cache.json"))
+/// Plaintext of an export bundle; on disk it is sealed like the file vault.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretBundle {
+    format: String,
+    created_at: u64,
+    profile: String,
+    secrets: BTreeMap<String, String>,
+    /// Feature toggles as stored by the webview; passed through untouched.
+    feature_toggles: Value,
 }
 
-#[tauri::command]
-fn read_cache_entry(app: AppHandle, key: String) -> Result<Option<Value>, String> {
-    let path = cache_file_path(&app)?;
-    if !path.exists() {
-        return Ok(None);
-    }
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleKeyPreview {
+    key: String,
+    /// A different value is stored now and would be replaced.
+    overwrite: bool,
+    /// The stored value already matches the bundle.
+    unchanged: bool,
+    /// Not in the secret registry; skipped on import.
+    unsupported: bool,
+}
 
-    let contents = std::fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read cache store {}: {e}", path.display()))?;
-    let parsed: Value = serde_json::from_str(&contents).unwrap_or_else(|_| Value::Object(Map::new()));
-    let Some(root) = parsed.as_object() else {
-        return Ok(None);
-    };
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundlePreview {
+    path: String,
+    created_at: u64,
+    profile: String,
+    keys: Vec<BundleKeyPreview>,
+    feature_toggles: Value,
+}
 
-    Ok(root.get(&key).cloned())
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleImport {
+    secrets: Vec<SecretBatchResult>,
+    feature_toggles: Value,
 }
 
-#[tauri::command]
-fn write_cache_entry(app: AppHandle, key: String, value: String) -> Result<(), String> {
-    let path = cache_file_path(&app)?;
+/// Outcome for one key of a batch command; `error` is set when that key failed.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretBatchResult {
+    key: String,
+    ok: bool,
+    status: Option<SecretStatus>,
+    error: Option<String>,
+}
 
-    let mut root: Map<String, Value> = if path.exists() {
-        let contents = std::fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read cache store {}: {e}", path.display()))?;
-        serde_json::from_str::<Value>(&contents)
-            .ok()
-            .and_then(|v| v.as_object().cloned())
-            .unwrap_or_default()
-    } else {
-        Map::new()
-    };
+/// Result of a profile switch. The switch itself is persisted before the sidecar restarts, so a
+/// failed restart is reported in `restart_error` rather than as a failed switch.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileSwitch {
+    #[serde(flatten)]
+    manifest: ProfileManifest,
+    restart_error: Option<String>,
+}
 
-    let parsed_value: Value = serde_json::from_str(&value)
-        .map_err(|e| format!("Invalid cache payload JSON: {e}"))?;
-    root.insert(key, parsed_value);
+#[derive(Deserialize)]
+struct SecretChange {
+    key: String,
+    /// `None` or blank deletes the key.
+    value: Option<String>,
+}
 
-    let serialized = serde_json::to_string_pretty(&Value::Object(root))
-        .map_err(|e| format!("Failed to serialize cache store: {e}"))?;
-    std::fs::write(&path, serialized)
-        .map_err(|e| format!("Failed to write cache store {}: {e}", path.display()))
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretStoreStatus {
+    backend: SecretBackend,
+    locked: bool,
+    vault_path: Option<String>,
+    vault_exists: bool,
+    reason: String,
+    write_only: bool,
 }
 
-fn logs_dir_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let dir = app
-        .path()
-        .app_log_dir()
-        .map_err(|e| format!("Failed to resolve app log dir: {e}"))?;
-    fs::create_dir_all(&dir)
-        .map_err(|e| format!("Failed to create app log dir {}: {e}", dir.display()))?;
-    Ok(dir)
+/// Reads a throwaway entry to find out whether a keyring backend (Secret Service, Keychain,
+/// Credential Manager) is actually reachable; "no such entry" means it is.
+fn probe_keyring() -> Result<(), String> {
+    let entry = Entry::new(KEYRING_SERVICE, "__keyring_probe__").map_err(|e| e.to_string())?;
+    match entry.get_password() {
+        Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
-fn sidecar_log_path(app: &AppHandle) -> Result<PathBuf, String> {
-    Ok(logs_dir_path(app)?.join(LOCAL_API_LOG_FILE))
+/// Write-only is the default; `WORLDMONITOR_SECRETS_WRITE_ONLY=0` lets `get_secret` return values again.
+fn secrets_write_only() -> bool {
+    !env::var("WORLDMONITOR_SECRETS_WRITE_ONLY").is_ok_and(|value| matches!(value.trim(), "0" | "false"))
 }
 
-fn desktop_log_path(app: &AppHandle) -> Result<PathBuf, String> {
-    Ok(logs_dir_path(app)?.join(DESKTOP_LOG_FILE))
+/// Lookup order from `WORLDMONITOR_SECRET_SOURCES` (comma-separated `env`, `dotenv`, `store`),
+/// defaulting to `env,dotenv,store`. Unknown names are ignored; leaving a layer out disables it.
+fn secret_layers() -> Vec<SecretLayer> {
+    let Ok(configured) = env::var("WORLDMONITOR_SECRET_SOURCES") else {
+        return vec![SecretLayer::Env, SecretLayer::Dotenv, SecretLayer::Store];
+    };
+    let mut layers = Vec::new();
+    for name in configured.split(',').map(|name| name.trim().to_ascii_lowercase()) {
+        let layer = match name.as_str() {
+            "env" => SecretLayer::Env,
+            "dotenv" => SecretLayer::Dotenv,
+            "store" | "keyring" | "vault" => SecretLayer::Store,
+            _ => continue,
+        };
+        if !layers.contains(&layer) {
+            layers.push(layer);
+        }
+    }
+    layers
 }
 
-fn append_desktop_log(app: &AppHandle, level: &str, message: &str) {
-    let Ok(path) = desktop_log_path(app) else {
-        return;
+/// Picks the secret backend from `WORLDMONITOR_SECRET_STORE` (`keyring`, `file` or `auto`, the
+/// default). `auto` uses the keyring when it answers and falls back to the file vault otherwise.
+/// `WORLDMONITOR_VAULT_PASSPHRASE` unlocks the vault without a prompt on headless machines.
+fn init_secret_store(app: &AppHandle) -> SecretStore {
+    let data_dir = app.path().app_data_dir().unwrap_or_default();
+    let mut vault = FileVault::new(data_dir.join(SECRET_VAULT_FILE));
+    let profiles_path = data_dir.join(SECRET_PROFILES_FILE);
+    let custom_manifest_path = data_dir.join(CUSTOM_SECRETS_FILE);
+    load_custom_secrets(app, &custom_manifest_path);
+    let dotenv_path = app
+        .path()
+        .app_config_dir()
+        .unwrap_or_default()
+        .join(SECRET_DOTENV_FILE);
+    let layers = secret_layers();
+    let profiles = ProfileManifest::load(&profiles_path);
+
+    let requested = env::var("WORLDMONITOR_SECRET_STORE")
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let (backend, reason) = match requested.as_str() {
+        "keyring" => (SecretBackend::Keyring, "selected by WORLDMONITOR_SECRET_STORE".to_string()),
+        "file" => (SecretBackend::File, "selected by WORLDMONITOR_SECRET_STORE".to_string()),
+        _ => match probe_keyring() {
+            Ok(()) => (SecretBackend::Keyring, "OS keyring available".to_string()),
+            Err(err) => (SecretBackend::File, format!("OS keyring unavailable: {err}")),
+        },
     };
 
-    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) else {
+    if backend == SecretBackend::File {
+        if let Ok(passphrase) = env::var("WORLDMONITOR_VAULT_PASSPHRASE") {
+            if let Err(err) = vault.unlock(&passphrase) {
+                append_desktop_log(app, "ERROR", &format!("secret vault unlock from environment failed: {err}"));
+            }
+        }
+    }
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!(
+            "secret store backend={} ({reason}){} profile={}",
+            if backend == SecretBackend::File { "file" } else { "keyring" },
+            if backend == SecretBackend::File && !vault.is_unlocked() { " locked" } else { "" },
+            profiles.active
+        ),
+    );
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!(
+            "secret source precedence: {} (dotenv {})",
+            layers.iter().map(|layer| layer.as_str()).collect::<Vec<_>>().join(" > "),
+            dotenv_path.display()
+        ),
+    );
+    let write_only = secrets_write_only();
+    if !write_only {
+        append_desktop_log(app, "WARN", "secrets write-only mode disabled; get_secret returns plaintext to the webview");
+    }
+    SecretStore {
+        backend,
+        reason,
+        vault: Mutex::new(vault),
+        write_only,
+        keyring_cache: Mutex::new(HashMap::new()),
+        profiles: Mutex::new(profiles),
+        profiles_path,
+        pending_import: Mutex::new(None),
+        layers,
+        dotenv_path,
+        custom_manifest_path,
+    }
+}
+
+fn load_custom_secrets(app: &AppHandle, path: &Path) {
+    let Ok(data) = fs::read_to_string(path) else {
         return;
     };
+    let manifest: Vec<CustomSecret> = match serde_json::from_str(&data) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            append_desktop_log(app, "ERROR", &format!("custom secret manifest {} unreadable: {err}", path.display()));
+            return;
+        }
+    };
+    for custom in &manifest {
+        if let Err(err) = secrets::register_custom_secret(custom) {
+            append_desktop_log(app, "WARN", &format!("skipping custom secret {}: {err}", custom.id));
+        }
+    }
+    append_desktop_log(app, "INFO", &format!("registered {} custom secret keys", manifest.len()));
+}
 
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-    let _ = writeln!(file, "[{timestamp}][{level}] {message}");
+fn save_custom_secrets(store: &SecretStore) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(&secrets::custom_secret_manifest())
+        .map_err(|e| format!("Failed to serialize custom secret manifest: {e}"))?;
+    if let Some(parent) = store.custom_manifest_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory {}: {e}", parent.display()))?;
+    }
+    fs::write(&store.custom_manifest_path, data).map_err(|e| {
+        format!(
+            "Failed to write custom secret manifest {}: {e}",
+            store.custom_manifest_path.display()
+        )
+    })
 }
 
-fn open_in_shell(arg: &str) -> Result<(), String> {
-    #[cfg(target_os = "macos")]
-    let mut command = {
-        let mut cmd = Command::new("open");
-        cmd.arg(arg);
-        cmd
-    };
+fn secret_entry(profile: &str, key: &str) -> Result<Entry, String> {
+    if secrets::find_secret(key).is_none() {
+        return Err(format!("Unsupported secret key: {key}"));
+    }
+    Entry::new(&profiles::keyring_service(KEYRING_SERVICE, profile), key)
+        .map_err(|e| format!("Keyring init failed: {e}"))
+}
 
-    #[cfg(target_os = "windows")]
-    let mut command = {
-        let mut cmd = Command::new("explorer");
-        cmd.arg(arg);
-        cmd
-    };
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
 
-    #[cfg(all(unix, not(target_os = "macos")))]
-    let mut command = {
-        let mut cmd = Command::new("xdg-open");
-        cmd.arg(arg);
-        cmd
-    };
+/// 256 bits from the OS CSPRNG, hex-encoded.
+fn generate_local_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate local API token: {e}"))?;
+    Ok(to_hex(&bytes))
+}
 
-    command
-        .spawn()
-        .map(|_| ())
-        .map_err(|e| format!("Failed to open {}: {e}", arg))
+fn hmac_sha256_hex(key: &str, message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    to_hex(&mac.finalize().into_bytes())
 }
 
-fn open_path_in_shell(path: &Path) -> Result<(), String> {
-    open_in_shell(&path.to_string_lossy())
+/// Derives a token scoped to one window that the sidecar accepts until `expires_at`.
+/// Format: `wm1.<window label>.<unix expiry>.<hex HMAC-SHA256(master token, prefix)>`.
+fn derive_window_token(master: &str, window_label: &str, expires_at: u64) -> String {
+    let payload = format!("wm1.{window_label}.{expires_at}");
+    let signature = hmac_sha256_hex(master, &payload);
+    format!("{payload}.{signature}")
+}
+
+fn window_tokens_enabled() -> bool {
+    env::var("LOCAL_API_WINDOW_TOKENS").is_ok_and(|value| matches!(value.trim(), "1" | "true"))
 }
 
 #[tauri::command]
-fn open_url(url: String) -> Result<(), String> {
-    if !url.starts_with("https://") && !url.starts_with("http://") {
-        return Err("Only http/https URLs are allowed".to_string());
+fn get_local_api_token(
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, LocalApiState>,
+) -> Result<String, String> {
+    let token = state
+        .token
+        .lock()
+        .map_err(|_| "Failed to lock local API token".to_string())?
+        .clone()
+        .ok_or_else(|| "Token not generated".to_string())?;
+    if !state.identity_verified.load(Ordering::SeqCst) {
+        let status = state.status.lock().map(|status| status.clone()).unwrap_or_default();
+        return Err(match (status.phase, status.last_error) {
+            (LocalApiPhase::Failed, Some(error)) => error,
+            _ => "Local API sidecar identity not verified yet".to_string(),
+        });
     }
-    open_in_shell(&url)
+    if !state.window_tokens {
+        return Ok(token);
+    }
+    let expires_at = window_token_expiry(SystemTime::now())?;
+    Ok(derive_window_token(&token, window.label(), expires_at))
 }
 
-fn open_logs_folder_impl(app: &AppHandle) -> Result<PathBuf, String> {
-    let dir = logs_dir_path(app)?;
-    open_path_in_shell(&dir)?;
-    Ok(dir)
+/// Unix seconds at which a window token minted at `now` stops being accepted.
+fn window_token_expiry(now: SystemTime) -> Result<u64, String> {
+    now.checked_add(LOCAL_API_WINDOW_TOKEN_TTL)
+        .and_then(|expiry| expiry.duration_since(UNIX_EPOCH).ok())
+        .map(|expiry| expiry.as_secs())
+        .ok_or_else(|| "System clock is before the Unix epoch".to_string())
 }
 
-fn open_sidecar_log_impl(app: &AppHandle) -> Result<PathBuf, String> {
-    let log_path = sidecar_log_path(app)?;
-    if !log_path.exists() {
-        File::create(&log_path)
-            .map_err(|e| format!("Failed to create sidecar log {}: {e}", log_path.display()))?;
+#[tauri::command]
+fn get_local_api_port(state: tauri::State<'_, LocalApiState>) -> Result<u16, String> {
+    if state.port == 0 {
+        return Err("Local API port not allocated".to_string());
     }
-    open_path_in_shell(&log_path)?;
-    Ok(log_path)
+    Ok(state.port)
 }
 
 #[tauri::command]
-fn open_logs_folder(app: AppHandle) -> Result<String, String> {
-    open_logs_folder_impl(&app).map(|path| path.display().to_string())
+fn get_local_api_status(app: AppHandle) -> LocalApiStatus {
+    local_api_status_snapshot(&app)
 }
 
 #[tauri::command]
-fn open_sidecar_log_file(app: AppHandle) -> Result<String, String> {
-    open_sidecar_log_impl(&app).map(|path| path.display().to_string())
+fn list_supported_secret_keys() -> Vec<SecretDescriptor> {
+    secrets::all_secrets().into_iter().copied().collect()
 }
 
-#[tauri::command]
-fn open_settings_window_command(app: AppHandle) -> Result<(), String> {
-    open_settings_window(&app)
+fn secret_store(app: &AppHandle) -> Result<tauri::State<'_, SecretStore>, String> {
+    app.try_state::<SecretStore>()
+        .ok_or_else(|| "Secret store not initialised".to_string())
 }
 
-#[tauri::command]
-fn close_settings_window(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("settings") {
-        window.close().map_err(|e| format!("Failed to close settings window: {e}"))?;
+fn lock_vault(store: &SecretStore) -> Result<std::sync::MutexGuard<'_, FileVault>, String> {
+    store
+        .vault
+        .lock()
+        .map_err(|_| "Failed to lock secret vault".to_string())
+}
+
+fn lock_keyring_cache(
+    store: &SecretStore,
+) -> Result<std::sync::MutexGuard<'_, HashMap<String, Option<String>>>, String> {
+    store
+        .keyring_cache
+        .lock()
+        .map_err(|_| "Failed to lock keyring cache".to_string())
+}
+
+fn lock_profiles(store: &SecretStore) -> Result<std::sync::MutexGuard<'_, ProfileManifest>, String> {
+    store
+        .profiles
+        .lock()
+        .map_err(|_| "Failed to lock secret profiles".to_string())
+}
+
+fn active_profile(store: &SecretStore) -> Result<String, String> {
+    Ok(lock_profiles(store)?.active.clone())
+}
+
+fn read_secret(app: &AppHandle, key: &str) -> Result<Option<String>, String> {
+    let store = secret_store(app)?;
+    let profile = active_profile(&store)?;
+    match store.backend {
+        SecretBackend::Keyring => {
+            let entry = secret_entry(&profile, key)?;
+            let mut cache = lock_keyring_cache(&store)?;
+            if let Some(value) = cache.get(key) {
+                return Ok(value.clone());
+            }
+            let value = match entry.get_password() {
+                Ok(value) => Some(value),
+                Err(keyring::Error::NoEntry) => None,
+                Err(err) => return Err(format!("Failed to read keyring secret: {err}")),
+            };
+            cache.insert(key.to_string(), value.clone());
+            Ok(value)
+        }
+        SecretBackend::File => {
+            secrets::find_secret(key).ok_or_else(|| format!("Unsupported secret key: {key}"))?;
+            lock_vault(&store)?.get(&profiles::vault_key(&profile, key))
+        }
     }
-    Ok(())
 }
 
-/// Fetch JSON from Polymarket Gamma API using native TLS (bypasses Cloudflare JA3 blocking).
-/// Called from frontend when browser CORS and sidecar Node.js TLS both fail.
-#[tauri::command]
-async fn fetch_polymarket(path: String, params: String) -> Result<String, String> {
-    let allowed = ["events", "markets", "tags"];
-    let segment = path.trim_start_matches('/');
-    if !allowed.iter().any(|a| segment.starts_with(a)) {
-        return Err("Invalid Polymarket path".into());
+fn write_secret(app: &AppHandle, key: &str, value: &str) -> Result<(), String> {
+    let store = secret_store(app)?;
+    let profile = active_profile(&store)?;
+    match store.backend {
+        SecretBackend::Keyring => {
+            secret_entry(&profile, key)?
+                .set_password(value)
+                .map_err(|e| format!("Failed to write keyring secret: {e}"))?;
+            lock_keyring_cache(&store)?.insert(key.to_string(), Some(value.to_string()));
+            Ok(())
+        }
+        SecretBackend::File => {
+            secrets::find_secret(key).ok_or_else(|| format!("Unsupported secret key: {key}"))?;
+            lock_vault(&store)?.set(&profiles::vault_key(&profile, key), value)
+        }
     }
-    let url = format!("https://gamma-api.polymarket.com/{}?{}", segment, params);
-    let client = reqwest::Client::builder()
-        .use_native_tls()
-        .build()
-        .map_err(|e| format!("HTTP client error: {e}"))?;
-    let resp = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await
-        .map_err(|e| format!("Polymarket fetch failed: {e}"))?;
-    if !resp.status().is_success() {
-        return Err(format!("Polymarket HTTP {}", resp.status()));
+}
+
+fn remove_secret(app: &AppHandle, key: &str) -> Result<(), String> {
+    let store = secret_store(app)?;
+    let profile = active_profile(&store)?;
+    match store.backend {
+        SecretBackend::Keyring => {
+            match secret_entry(&profile, key)?.delete_credential() {
+                Ok(_) | Err(keyring::Error::NoEntry) => {}
+                Err(err) => return Err(format!("Failed to delete keyring secret: {err}")),
+            }
+            lock_keyring_cache(&store)?.insert(key.to_string(), None);
+            Ok(())
+        }
+        SecretBackend::File => {
+            secrets::find_secret(key).ok_or_else(|| format!("Unsupported secret key: {key}"))?;
+            lock_vault(&store)?.remove(&profiles::vault_key(&profile, key))
+        }
     }
-    resp.text().await.map_err(|e| format!("Read body failed: {e}"))
 }
 
-fn open_settings_window(app: &AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("settings") {
-        let _ = window.show();
-        window
-            .set_focus()
-            .map_err(|e| format!("Failed to focus settings window: {e}"))?;
-        return Ok(());
+/// Writes or removes one secret in the active backend (keyring or file vault). `set_secrets`
+/// uses it on the keyring path both to apply changes and to roll them back.
+fn apply_secret_change(app: &AppHandle, key: &str, value: Option<&str>) -> Result<(), String> {
+    match value {
+        Some(value) => write_secret(app, key, value),
+        None => remove_secret(app, key),
     }
+}
 
-    WebviewWindowBuilder::new(app, "settings", WebviewUrl::App("settings.html".into()))
-        .title("World Monitor Settings")
-        .inner_size(980.0, 760.0)
-        .min_inner_size(820.0, 620.0)
-        .resizable(true)
-        .build()
-        .map_err(|e| format!("Failed to create settings window: {e}"))?;
+fn read_dotenv(store: &SecretStore) -> BTreeMap<String, String> {
+    fs::read_to_string(&store.dotenv_path)
+        .map(|contents| secrets::parse_dotenv(&contents))
+        .unwrap_or_default()
+}
 
-    Ok(())
+/// Every non-empty value for `key`, in precedence order. A store error is only returned when
+/// no higher-precedence source has a value.
+fn lookup_secret(app: &AppHandle, key: &str, first_only: bool) -> Result<Vec<ResolvedSecret>, String> {
+    secrets::find_secret(key).ok_or_else(|| format!("Unsupported secret key: {key}"))?;
+    let store = secret_store(app)?;
+    let mut found = Vec::new();
+    for layer in &store.layers {
+        let resolved = match layer {
+            SecretLayer::Env => env::var(key).ok().map(|value| ResolvedSecret { value, source: SecretSource::Env }),
+            SecretLayer::Dotenv => read_dotenv(&store)
+                .remove(key)
+                .map(|value| ResolvedSecret { value, source: SecretSource::Dotenv }),
+            SecretLayer::Store => {
+                let source = match store.backend {
+                    SecretBackend::Keyring => SecretSource::Keyring,
+                    SecretBackend::File => SecretSource::Vault,
+                };
+                match read_secret(app, key) {
+                    Ok(value) => value.map(|value| ResolvedSecret { value, source }),
+                    Err(err) if found.is_empty() => return Err(err),
+                    Err(_) => None,
+                }
+            }
+        };
+        if let Some(resolved) = resolved.filter(|resolved| !resolved.value.trim().is_empty()) {
+            found.push(ResolvedSecret {
+                value: resolved.value.trim().to_string(),
+                source: resolved.source,
+            });
+            if first_only {
+                break;
+            }
+        }
+    }
+    Ok(found)
 }
 
-fn build_app_menu(handle: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
-    let settings_item = MenuItem::with_id(
-        handle,
-        MENU_FILE_SETTINGS_ID,
-        "Settings...",
-        true,
-        Some("CmdOrCtrl+,"),
-    )?;
-    let separator = PredefinedMenuItem::separator(handle)?;
-    let quit_item = PredefinedMenuItem::quit(handle, Some("Quit"))?;
-    let file_menu =
-        Submenu::with_items(handle, "File", true, &[&settings_item, &separator, &quit_item])?;
+/// The value the sidecar should see for `key`, after applying source precedence.
+fn resolve_secret(app: &AppHandle, key: &str) -> Result<Option<ResolvedSecret>, String> {
+    Ok(lookup_secret(app, key, true)?.into_iter().next())
+}
 
-    let about_metadata = AboutMetadata {
-        name: Some("World Monitor".into()),
-        version: Some(env!("CARGO_PKG_VERSION").into()),
-        copyright: Some("\u{00a9} 2025 Elie Habib".into()),
-        website: Some("https://worldmonitor.app".into()),
-        website_label: Some("worldmonitor.app".into()),
-        ..Default::default()
+fn secret_status(app: &AppHandle, key: &str) -> Result<SecretStatus, String> {
+    let descriptor = secrets::find_secret(key).ok_or_else(|| format!("Unsupported secret key: {key}"))?;
+    Ok(secrets::status(descriptor, resolve_secret(app, key)?.as_ref()))
+}
+
+/// Records a secret command in the audit trail. Only the outcome is kept, never the value;
+/// a failure to write the trail is logged but doesn't fail the command.
+fn audit_secret_access<T>(
+    app: &AppHandle,
+    window: &tauri::WebviewWindow,
+    action: &str,
+    key: Option<&str>,
+    result: &Result<T, String>,
+) {
+    let event = AuditEvent {
+        timestamp: unix_millis(),
+        action: action.to_string(),
+        key: key.map(str::to_string),
+        window: window.label().to_string(),
+        profile: secret_store(app)
+            .and_then(|store| active_profile(&store))
+            .unwrap_or_default(),
+        success: result.is_ok(),
+        error: result.as_ref().err().cloned(),
+    };
+    if let Err(err) = secret_audit_log_path(app).and_then(|path| audit::append(&path, &event)) {
+        append_desktop_log(app, "ERROR", &format!("secret audit write failed: {err}"));
+    }
+}
+
+/// Secret audit events, newest first, optionally filtered by key, action, window and time.
+#[tauri::command]
+fn get_secret_audit_log(app: AppHandle, query: Option<AuditQuery>) -> Result<Vec<AuditEvent>, String> {
+    audit::query(&secret_audit_log_path(&app)?, &query.unwrap_or_default())
+}
+
+#[tauri::command]
+fn get_secret(app: AppHandle, window: tauri::WebviewWindow, key: String) -> Result<Option<String>, String> {
+    let result = secret_store(&app).and_then(|store| {
+        if store.write_only {
+            return Err("Secrets are write-only; use get_secret_status".to_string());
+        }
+        read_secret(&app, &key)
+    });
+    audit_secret_access(&app, &window, "get", Some(&key), &result);
+    result
+}
+
+/// Presence, masked preview and format check for a stored secret, without the value itself.
+#[tauri::command]
+fn get_secret_status(app: AppHandle, key: String) -> Result<SecretStatus, String> {
+    secret_status(&app, &key)
+}
+
+/// Reports, for every registered key, which sources hold a value and which one wins.
+#[tauri::command]
+fn get_secret_sources(app: AppHandle) -> Result<SecretSourceReport, String> {
+    let store = secret_store(&app)?;
+    let keys = secrets::secret_ids()
+        .into_iter()
+        .map(|key| match lookup_secret(&app, key, false) {
+            Ok(found) => SecretSourceEntry {
+                key: key.to_string(),
+                effective: found.first().map(|resolved| resolved.source),
+                available: found.iter().map(|resolved| resolved.source).collect(),
+                error: None,
+            },
+            Err(error) => SecretSourceEntry {
+                key: key.to_string(),
+                effective: None,
+                available: Vec::new(),
+                error: Some(error),
+            },
+        })
+        .collect();
+    Ok(SecretSourceReport {
+        precedence: store.layers.clone(),
+        dotenv_path: store.dotenv_path.display().to_string(),
+        dotenv_exists: store.dotenv_path.exists(),
+        keys,
+    })
+}
+
+/// Status of many keys in one call (every registered key when `keys` is omitted).
+/// Failures are reported per key rather than failing the whole batch.
+#[tauri::command]
+fn get_secrets_status(app: AppHandle, keys: Option<Vec<String>>) -> Vec<SecretBatchResult> {
+    let keys = keys.unwrap_or_else(|| secrets::secret_ids().into_iter().map(str::to_string).collect());
+    keys.into_iter()
+        .map(|key| {
+            match secret_status(&app, &key) {
+                Ok(status) => SecretBatchResult { key, ok: true, status: Some(status), error: None },
+                Err(error) => SecretBatchResult { key, ok: false, status: None, error: Some(error) },
+            }
+        })
+        .collect()
+}
+
+/// Sets (or, with a blank value, deletes) several secrets at once. Unknown keys reject the
+/// whole batch up front. The file vault is re-sealed once; keyring writes are applied in
+/// order and, if one fails, the keys already written are restored to their previous values.
+#[tauri::command]
+async fn set_secrets(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    changes: Vec<SecretChange>,
+) -> Result<Vec<SecretBatchResult>, String> {
+    let changes: Vec<(String, Option<String>)> = changes
+        .into_iter()
+        .map(|change| {
+            let value = change.value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+            (change.key, value)
+        })
+        .collect();
+    let actions: HashMap<String, &str> = changes
+        .iter()
+        .map(|(key, value)| (key.clone(), if value.is_some() { "set" } else { "delete" }))
+        .collect();
+    let result = apply_secret_changes(&app, changes);
+    audit_secret_batch(&app, &window, &actions, &result);
+    result
+}
+
+/// One audit event per key of a batch, with that key's own outcome.
+fn audit_secret_batch(
+    app: &AppHandle,
+    window: &tauri::WebviewWindow,
+    actions: &HashMap<String, &str>,
+    result: &Result<Vec<SecretBatchResult>, String>,
+) {
+    match result {
+        Ok(results) => {
+            for entry in results {
+                let outcome = match &entry.error {
+                    Some(error) => Err(error.clone()),
+                    None => Ok(()),
+                };
+                let action = actions.get(&entry.key).copied().unwrap_or("set");
+                audit_secret_access(app, window, action, Some(&entry.key), &outcome);
+            }
+        }
+        Err(error) => {
+            for (key, action) in actions {
+                audit_secret_access::<()>(app, window, action, Some(key), &Err(error.clone()));
+            }
+        }
+    }
+}
+
+fn apply_secret_changes(
+    app: &AppHandle,
+    changes: Vec<(String, Option<String>)>,
+) -> Result<Vec<SecretBatchResult>, String> {
+    if let Some((key, _)) = changes.iter().find(|(key, _)| secrets::find_secret(key).is_none()) {
+        return Err(format!("Unsupported secret key: {key}"));
+    }
+
+    let store = secret_store(app)?;
+    // Keys whose keyring rollback failed, so they still hold the new value.
+    let mut stuck: BTreeMap<String, String> = BTreeMap::new();
+    let outcome: Result<(), (Option<String>, String)> = match store.backend {
+        SecretBackend::File => active_profile(&store)
+            .and_then(|profile| {
+                let scoped: Vec<(String, Option<String>)> = changes
+                    .iter()
+                    .map(|(key, value)| (profiles::vault_key(&profile, key), value.clone()))
+                    .collect();
+                lock_vault(&store)?.apply(&scoped)
+            })
+            .map_err(|err| (None, err)),
+        SecretBackend::Keyring => {
+            let mut applied: Vec<(&str, Option<String>)> = Vec::new();
+            let mut failure = None;
+            for (key, value) in &changes {
+                let previous = match read_secret(app, key) {
+                    Ok(previous) => previous,
+                    Err(err) => {
+                        failure = Some((Some(key.clone()), err));
+                        break;
+                    }
+                };
+                if let Err(err) = apply_secret_change(app, key, value.as_deref()) {
+                    failure = Some((Some(key.clone()), err));
+                    break;
+                }
+                applied.push((key, previous));
+            }
+            if failure.is_some() {
+                for (key, previous) in applied.iter().rev() {
+                    if let Err(err) = apply_secret_change(app, key, previous.as_deref()) {
+                        append_desktop_log(app, "ERROR", &format!("failed to roll back secret key={key}: {err}"));
+                        stuck.insert(key.to_string(), err);
+                    }
+                }
+            }
+            failure.map_or(Ok(()), Err)
+        }
+    };
+
+    if let Err((failed_key, error)) = outcome {
+        append_desktop_log(app, "WARN", &format!("batch secret update rolled back: {error}"));
+        if !stuck.is_empty() {
+            // The keyring kept these new values; the sidecar must see what the keyring holds.
+            let keys: Vec<&str> = stuck.keys().map(String::as_str).collect();
+            sync_secrets_with_local_api(app, &keys);
+        }
+        return Ok(changes
+            .into_iter()
+            .map(|(key, _)| {
+                if let Some(rollback_error) = stuck.get(&key) {
+                    return SecretBatchResult {
+                        status: secret_status(app, &key).ok(),
+                        error: Some(format!(
+                            "Batch failed and this key could not be rolled back, so it kept the new value: {rollback_error}"
+                        )),
+                        key,
+                        ok: false,
+                    };
+                }
+                let error = match &failed_key {
+                    Some(failed_key) if *failed_key != key => format!("Not applied: {failed_key} failed"),
+                    _ => error.clone(),
+                };
+                SecretBatchResult { key, ok: false, status: None, error: Some(error) }
+            })
+            .collect());
+    }
+
+    let keys: Vec<&str> = changes.iter().map(|(key, _)| key.as_str()).collect();
+    sync_secrets_with_local_api(app, &keys);
+    Ok(changes
+        .iter()
+        .map(|(key, _)| SecretBatchResult {
+            key: key.clone(),
+            ok: true,
+            status: secret_status(app, key).ok(),
+            error: None,
+        })
+        .collect())
+}
+
+/// Trims the value like `set_secrets` does; a blank value deletes the key.
+#[tauri::command]
+async fn set_secret(app: AppHandle, window: tauri::WebviewWindow, key: String, value: String) -> Result<(), String> {
+    let value = value.trim();
+    let (action, result) = if value.is_empty() {
+        ("delete", remove_secret(&app, &key))
+    } else {
+        ("set", write_secret(&app, &key, value))
+    };
+    audit_secret_access(&app, &window, action, Some(&key), &result);
+    result?;
+    sync_secrets_with_local_api(&app, &[key.as_str()]);
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_secret(app: AppHandle, window: tauri::WebviewWindow, key: String) -> Result<(), String> {
+    let result = remove_secret(&app, &key);
+    audit_secret_access(&app, &window, "delete", Some(&key), &result);
+    result?;
+    sync_secrets_with_local_api(&app, &[key.as_str()]);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_secret_store_status(app: AppHandle) -> Result<SecretStoreStatus, String> {
+    let store = secret_store(&app)?;
+    let vault = lock_vault(&store)?;
+    let file_backend = store.backend == SecretBackend::File;
+    Ok(SecretStoreStatus {
+        backend: store.backend,
+        locked: file_backend && !vault.is_unlocked(),
+        vault_path: file_backend.then(|| vault.path().display().to_string()),
+        vault_exists: file_backend && vault.exists(),
+        reason: store.reason.clone(),
+        write_only: store.write_only,
+    })
+}
+
+/// Unlocks (or, on first use, creates) the file vault, then relaunches the sidecar so it
+/// picks up the secrets that were unreadable while the vault was locked.
+#[tauri::command]
+async fn unlock_secret_vault(app: AppHandle, window: tauri::WebviewWindow, passphrase: String) -> Result<(), String> {
+    let result = secret_store(&app).and_then(|store| {
+        if store.backend != SecretBackend::File {
+            return Err("Secrets are stored in the OS keyring; there is no vault to unlock".to_string());
+        }
+        lock_vault(&store)?.unlock(&passphrase)
+    });
+    audit_secret_access(&app, &window, "unlock-vault", None, &result);
+    result?;
+    append_desktop_log(&app, "INFO", "secret vault unlocked");
+    restart_local_api_if_running(&app)
+}
+
+#[tauri::command]
+fn list_secret_profiles(app: AppHandle) -> Result<ProfileManifest, String> {
+    let store = secret_store(&app)?;
+    let manifest = lock_profiles(&store)?.clone();
+    Ok(manifest)
+}
+
+/// Makes `name` the active profile, creating it if it doesn't exist yet, and relaunches
+/// the sidecar with that profile's secrets.
+#[tauri::command]
+async fn switch_secret_profile(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    name: String,
+) -> Result<ProfileSwitch, String> {
+    let result = activate_secret_profile(&app, &name);
+    audit_secret_access(&app, &window, "switch-profile", None, &result);
+    result
+}
+
+fn activate_secret_profile(app: &AppHandle, name: &str) -> Result<ProfileSwitch, String> {
+    profiles::validate_profile_name(name)?;
+    let store = secret_store(app)?;
+    let manifest = {
+        let mut manifest = lock_profiles(&store)?;
+        if manifest.active == name {
+            return Ok(ProfileSwitch {
+                manifest: manifest.clone(),
+                restart_error: None,
+            });
+        }
+        let mut updated = manifest.clone();
+        if !updated.profiles.iter().any(|profile| profile == name) {
+            updated.profiles.push(name.to_string());
+        }
+        updated.active = name.to_string();
+        updated.save(&store.profiles_path)?;
+        *manifest = updated.clone();
+        updated
+    };
+    lock_keyring_cache(&store)?.clear();
+    append_desktop_log(app, "INFO", &format!("secret profile switched to {name}"));
+    let restart_error = restart_local_api_if_running(app).err();
+    if let Some(err) = &restart_error {
+        append_desktop_log(app, "ERROR", &format!("sidecar restart after profile switch failed: {err}"));
+    }
+    Ok(ProfileSwitch { manifest, restart_error })
+}
+
+/// Removes a profile and every secret stored under it. The default and active profiles can't be deleted.
+#[tauri::command]
+fn delete_secret_profile(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    name: String,
+) -> Result<ProfileManifest, String> {
+    let result = remove_secret_profile(&app, &name);
+    audit_secret_access(&app, &window, "delete-profile", None, &result);
+    result
+}
+
+fn remove_secret_profile(app: &AppHandle, name: &str) -> Result<ProfileManifest, String> {
+    let store = secret_store(app)?;
+    let mut manifest = lock_profiles(&store)?;
+    if name == profiles::DEFAULT_PROFILE {
+        return Err("Cannot delete the default profile".to_string());
+    }
+    if name == manifest.active {
+        return Err(format!("Cannot delete the active profile {name}; switch to another first"));
+    }
+    if !manifest.profiles.iter().any(|profile| profile == name) {
+        return Err(format!("Unknown secret profile: {name}"));
+    }
+    match store.backend {
+        SecretBackend::Keyring => {
+            for key in secrets::secret_ids() {
+                match secret_entry(name, key)?.delete_credential() {
+                    Ok(_) | Err(keyring::Error::NoEntry) => {}
+                    Err(err) => return Err(format!("Failed to delete keyring secret {key}: {err}")),
+                }
+            }
+        }
+        SecretBackend::File => {
+            let removals: Vec<(String, Option<String>)> =
+                secrets::secret_ids().into_iter().map(|key| (profiles::vault_key(name, key), None)).collect();
+            lock_vault(&store)?.apply(&removals)?;
+        }
+    }
+    let mut updated = manifest.clone();
+    updated.profiles.retain(|profile| profile != name);
+    updated.save(&store.profiles_path)?;
+    *manifest = updated.clone();
+    append_desktop_log(app, "INFO", &format!("secret profile {name} deleted"));
+    Ok(updated)
+}
+
+/// Checks a secret's format and, when `probe` is set, asks the provider whether it accepts it.
+/// Without `value` the stored secret is validated, which is audited as a read since the value
+/// may be sent to the provider.
+#[tauri::command]
+async fn validate_secret(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    key: String,
+    value: Option<String>,
+    probe: Option<bool>,
+) -> Result<SecretVerdict, String> {
+    let descriptor = secrets::find_secret(&key).ok_or_else(|| format!("Unsupported secret key: {key}"))?;
+    let value = match value {
+        Some(value) => value,
+        None => {
+            let result = resolve_secret(&app, &key);
+            audit_secret_access(&app, &window, "validate", Some(&key), &result);
+            result?.map(|resolved| resolved.value).unwrap_or_default()
+        }
+    };
+    Ok(secrets::validate(descriptor, &value, probe.unwrap_or(false)).await)
+}
+
+/// Registers a `CUSTOM_` key for a handler added under `api/`. It is stored, resolved and
+/// injected into the sidecar like a built-in key, and kept in `custom-secrets.json`.
+#[tauri::command]
+async fn register_custom_secret(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    id: String,
+    label: Option<String>,
+    kind: Option<SecretKind>,
+    sensitive: Option<bool>,
+) -> Result<SecretDescriptor, String> {
+    let custom = CustomSecret {
+        id: id.trim().to_string(),
+        label: label.unwrap_or_default(),
+        kind: kind.unwrap_or(SecretKind::ApiKey),
+        sensitive: sensitive.unwrap_or(true),
+    };
+    let result = secret_store(&app).and_then(|store| {
+        let descriptor = secrets::register_custom_secret(&custom)?;
+        save_custom_secrets(&store)?;
+        Ok(*descriptor)
+    });
+    audit_secret_access(&app, &window, "register-custom", Some(&custom.id), &result);
+    if result.is_ok() {
+        // An env or .env value for the new name reaches the sidecar without a restart.
+        sync_secrets_with_local_api(&app, &[custom.id.as_str()]);
+    }
+    result
+}
+
+/// Removes a custom key and its stored value in every profile.
+#[tauri::command]
+async fn unregister_custom_secret(app: AppHandle, window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let result = remove_custom_secret(&app, &id);
+    audit_secret_access(&app, &window, "unregister-custom", Some(&id), &result);
+    result?;
+    sync_secrets_with_local_api(&app, &[id.as_str()]);
+    Ok(())
+}
+
+fn remove_custom_secret(app: &AppHandle, id: &str) -> Result<(), String> {
+    secrets::validate_custom_secret_id(id)?;
+    secrets::find_secret(id).ok_or_else(|| format!("Unknown custom secret: {id}"))?;
+    let store = secret_store(app)?;
+    let profile_names = lock_profiles(&store)?.profiles.clone();
+    match store.backend {
+        SecretBackend::Keyring => {
+            for profile in &profile_names {
+                match secret_entry(profile, id)?.delete_credential() {
+                    Ok(_) | Err(keyring::Error::NoEntry) => {}
+                    Err(err) => return Err(format!("Failed to delete keyring secret: {err}")),
+                }
+            }
+            lock_keyring_cache(&store)?.remove(id);
+        }
+        SecretBackend::File => {
+            let removals: Vec<(String, Option<String>)> = profile_names
+                .iter()
+                .map(|profile| (profiles::vault_key(profile, id), None))
+                .collect();
+            lock_vault(&store)?.apply(&removals)?;
+        }
+    }
+    secrets::unregister_custom_secret(id)?;
+    save_custom_secrets(&store)
+}
+
+/// Writes every stored secret of the active profile, plus the webview's feature toggles, to a
+/// passphrase-encrypted bundle chosen in a native save dialog. Returns `None` if the user cancels.
+#[tauri::command]
+async fn export_secrets_bundle(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    passphrase: String,
+    feature_toggles: Value,
+) -> Result<Option<String>, String> {
+    let result = write_secrets_bundle(&app, &passphrase, feature_toggles);
+    if !matches!(result, Ok(None)) {
+        audit_secret_access(&app, &window, "export", None, &result);
+    }
+    result
+}
+
+fn write_secrets_bundle(app: &AppHandle, passphrase: &str, feature_toggles: Value) -> Result<Option<String>, String> {
+    let store = secret_store(app)?;
+    let profile = active_profile(&store)?;
+    let mut stored = BTreeMap::new();
+    for key in secrets::secret_ids() {
+        if let Some(value) = read_secret(app, key)?.filter(|value| !value.trim().is_empty()) {
+            stored.insert(key.to_string(), value);
+        }
+    }
+    let count = stored.len();
+    let bundle = SecretBundle {
+        format: SECRET_BUNDLE_FORMAT.to_string(),
+        created_at: unix_millis(),
+        profile: profile.clone(),
+        secrets: stored,
+        feature_toggles,
+    };
+    let plaintext = serde_json::to_vec(&bundle).map_err(|e| format!("Failed to serialize bundle: {e}"))?;
+    let sealed = vault::seal(passphrase, &plaintext)?;
+
+    let Some(path) = app
+        .dialog()
+        .file()
+        .set_title("Export World Monitor keys")
+        .set_file_name(format!("world-monitor-{profile}.{SECRET_BUNDLE_EXTENSION}"))
+        .add_filter("World Monitor bundle", &[SECRET_BUNDLE_EXTENSION])
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = path.into_path().map_err(|e| format!("Invalid export path: {e}"))?;
+    fs::write(&path, sealed).map_err(|e| format!("Failed to write bundle {}: {e}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o600));
+    }
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!("exported {count} secrets from profile {profile} to {}", path.display()),
+    );
+    Ok(Some(path.display().to_string()))
+}
+
+/// Opens a bundle picked in a native dialog and reports which keys an import would add or
+/// overwrite in the active profile. Nothing is written until `import_secrets_bundle`.
+#[tauri::command]
+async fn preview_secrets_bundle(app: AppHandle, passphrase: String) -> Result<Option<BundlePreview>, String> {
+    let Some(path) = app
+        .dialog()
+        .file()
+        .set_title("Import World Monitor keys")
+        .add_filter("World Monitor bundle", &[SECRET_BUNDLE_EXTENSION])
+        .blocking_pick_file()
+    else {
+        return Ok(None);
+    };
+    let path = path.into_path().map_err(|e| format!("Invalid import path: {e}"))?;
+    let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read bundle {}: {e}", path.display()))?;
+    let plaintext = vault::open(&passphrase, &data).map_err(|e| format!("Failed to open bundle: {e}"))?;
+    let bundle: SecretBundle =
+        serde_json::from_slice(&plaintext).map_err(|e| format!("Bundle contents are corrupt: {e}"))?;
+    if bundle.format != SECRET_BUNDLE_FORMAT {
+        return Err(format!("Not a World Monitor secrets bundle (format {})", bundle.format));
+    }
+
+    let mut keys = Vec::new();
+    for (key, value) in &bundle.secrets {
+        let unsupported = secrets::find_secret(key).is_none();
+        let current = if unsupported { None } else { read_secret(&app, key)? };
+        keys.push(BundleKeyPreview {
+            key: key.clone(),
+            overwrite: current.as_deref().is_some_and(|current| current.trim() != value.trim()),
+            unchanged: current.as_deref().is_some_and(|current| current.trim() == value.trim()),
+            unsupported,
+        });
+    }
+    let preview = BundlePreview {
+        path: path.display().to_string(),
+        created_at: bundle.created_at,
+        profile: bundle.profile.clone(),
+        keys,
+        feature_toggles: bundle.feature_toggles.clone(),
+    };
+    let store = secret_store(&app)?;
+    *store
+        .pending_import
+        .lock()
+        .map_err(|_| "Failed to lock pending import".to_string())? = Some(bundle);
+    Ok(Some(preview))
+}
+
+/// Applies the bundle from the last preview to the active profile, all keys or only `keys`.
+/// Keys absent from the bundle are left alone. Returns the bundle's feature toggles for the webview.
+#[tauri::command]
+async fn import_secrets_bundle(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    keys: Option<Vec<String>>,
+) -> Result<BundleImport, String> {
+    let store = secret_store(&app)?;
+    let bundle = store
+        .pending_import
+        .lock()
+        .map_err(|_| "Failed to lock pending import".to_string())?
+        .take()
+        .ok_or_else(|| "No bundle to import; preview one first".to_string())?;
+    let changes = bundle
+        .secrets
+        .into_iter()
+        .filter(|(key, _)| secrets::find_secret(key).is_some())
+        .filter(|(key, _)| keys.as_ref().is_none_or(|keys| keys.contains(key)))
+        .map(|(key, value)| (key, Some(value.trim().to_string())))
+        .collect::<Vec<_>>();
+    let count = changes.len();
+    let actions: HashMap<String, &str> = changes.iter().map(|(key, _)| (key.clone(), "import")).collect();
+    let results = apply_secret_changes(&app, changes);
+    audit_secret_batch(&app, &window, &actions, &results);
+    let results = results?;
+    append_desktop_log(
+        &app,
+        "INFO",
+        &format!("imported {count} secrets from bundle (exported from profile {})", bundle.profile),
+    );
+    Ok(BundleImport {
+        secrets: results,
+        feature_toggles: bundle.feature_toggles,
+    })
+}
+
+/// Cache limits from `WORLDMONITOR_CACHE_TTL_HOURS` (default TTL for entries written without
+/// one; `0` disables it) and `WORLDMONITOR_CACHE_MAX_MB` (quota before LRU eviction).
+fn cache_policy(app: &AppHandle) -> CachePolicy {
+    let mut policy = CachePolicy::default();
+    if let Ok(hours) = env::var("WORLDMONITOR_CACHE_TTL_HOURS") {
+        match hours.trim().parse::<u64>() {
+            Ok(0) => policy.default_ttl_ms = None,
+            Ok(hours) => policy.default_ttl_ms = Some(hours.saturating_mul(60 * 60 * 1000)),
+            Err(_) => append_desktop_log(app, "WARN", &format!("ignoring invalid WORLDMONITOR_CACHE_TTL_HOURS: {hours}")),
+        }
+    }
+    if let Ok(megabytes) = env::var("WORLDMONITOR_CACHE_MAX_MB") {
+        match megabytes.trim().parse::<u64>() {
+            Ok(megabytes) if megabytes > 0 => policy.max_bytes = megabytes.saturating_mul(1024 * 1024),
+            _ => append_desktop_log(app, "WARN", &format!("ignoring invalid WORLDMONITOR_CACHE_MAX_MB: {megabytes}")),
+        }
+    }
+    policy
+}
+
+/// Opens the cache database and folds in the legacy JSON cache on first run. A database that
+/// fails its integrity check is quarantined and reported; once the cache is known good it is
+/// snapshotted as the backup the next recovery would restore. If the database can't be opened
+/// at all the session falls back to an in-memory store rather than losing caching.
+fn init_cache_store(app: &AppHandle) -> CacheStore {
+    let data_dir = app.path().app_data_dir().unwrap_or_default();
+    let mut store = match CacheStore::open(&data_dir.join(CACHE_DB_FILE)) {
+        Ok(store) => store,
+        Err(err) => {
+            append_desktop_log(app, "ERROR", &format!("{err}; caching in memory for this session"));
+            CacheStore::in_memory().expect("in-memory cache database")
+        }
+    };
+    store.set_policy(cache_policy(app));
+    if let Some(recovery) = store.recovery() {
+        append_desktop_log(
+            app,
+            "ERROR",
+            &format!(
+                "cache database corrupt ({}); quarantined to {}, {}",
+                recovery.reason,
+                recovery.quarantined.display(),
+                if recovery.restored_from_backup { "restored last-known-good backup" } else { "starting empty" }
+            ),
+        );
+    }
+    match store.migrate_json(&data_dir.join(LEGACY_CACHE_FILE), unix_millis()) {
+        Ok(Some(count)) => append_desktop_log(
+            app,
+            "INFO",
+            &format!("migrated {count} cache entries from {LEGACY_CACHE_FILE} to {}", store.path().display()),
+        ),
+        Ok(None) => {}
+        Err(err) => append_desktop_log(app, "ERROR", &format!("cache migration failed: {err}")),
+    }
+    store
+}
+
+/// Broadcasts `change` on `cache-changed` to every window, then sends it on each matching
+/// subscription's own event to the window that subscribed.
+fn notify_cache_change(
+    app: &AppHandle,
+    action: CacheChangeAction,
+    key: Option<String>,
+    prefix: Option<String>,
+    source: Option<&str>,
+) {
+    let change = CacheChange {
+        action,
+        key,
+        prefix,
+        timestamp: unix_millis(),
+        source: source.map(str::to_string),
+    };
+    if let Err(err) = app.emit(CACHE_CHANGED_EVENT, change.clone()) {
+        append_desktop_log(app, "WARN", &format!("failed to emit {CACHE_CHANGED_EVENT}: {err}"));
+    }
+    let Some(subscriptions) = app.try_state::<CacheSubscriptions>() else {
+        return;
+    };
+    let Ok(entries) = subscriptions.entries.lock() else {
+        return;
+    };
+    for (event, window) in cache_change_recipients(&entries, &change) {
+        if let Err(err) = app.emit_to(EventTarget::webview_window(window), event, change.clone()) {
+            append_desktop_log(app, "WARN", &format!("failed to emit {event}: {err}"));
+        }
+    }
+}
+
+/// Subscription events `change` should go out on, each with the window it is sent to.
+fn cache_change_recipients<'a>(
+    entries: &'a HashMap<String, CacheSubscription>,
+    change: &CacheChange,
+) -> Vec<(&'a str, &'a str)> {
+    entries
+        .iter()
+        .filter(|(_, subscription)| subscription.prefixes.iter().any(|prefix| change.affects(prefix)))
+        .map(|(event, subscription)| (event.as_str(), subscription.window.as_str()))
+        .collect()
+}
+
+/// Called when a window is destroyed so its subscriptions don't outlive it.
+fn drop_cache_subscriptions(app: &AppHandle, window: &str) {
+    let Some(subscriptions) = app.try_state::<CacheSubscriptions>() else {
+        return;
+    };
+    let Ok(mut entries) = subscriptions.entries.lock() else {
+        return;
+    };
+    entries.retain(|_, subscription| subscription.window != window);
+}
+
+fn notify_cache_evictions(app: &AppHandle, evicted: Vec<String>, source: Option<&str>) {
+    for key in evicted {
+        notify_cache_change(app, CacheChangeAction::Evict, Some(key), None, source);
+    }
+}
+
+/// Drops expired entries and enforces the quota at launch and every `CACHE_SWEEP_INTERVAL`, so
+/// entries nobody reads again still go away. The same thread refreshes the last-known-good
+/// backup, at most once per `CACHE_BACKUP_INTERVAL` and only after the cache changed, so the
+/// snapshot's full copy and fsync stay off the launch path.
+fn spawn_cache_sweeper(app: &AppHandle) {
+    let handle = app.clone();
+    let spawned = thread::Builder::new().name("cache-sweeper".into()).spawn(move || loop {
+        match handle.state::<CacheStore>().sweep(unix_millis()) {
+            Ok(sweep) => {
+                if sweep.expired + sweep.evicted.len() > 0 {
+                    append_desktop_log(
+                        &handle,
+                        "INFO",
+                        &format!(
+                            "cache sweep removed {} expired and {} evicted entries",
+                            sweep.expired,
+                            sweep.evicted.len()
+                        ),
+                    );
+                }
+                notify_cache_evictions(&handle, sweep.evicted, None);
+            }
+            Err(err) => append_desktop_log(&handle, "WARN", &format!("cache sweep failed: {err}")),
+        }
+        match handle.state::<CacheStore>().backup_if_due(CACHE_BACKUP_INTERVAL) {
+            Ok(Some(path)) => append_desktop_log(&handle, "INFO", &format!("cache backed up to {}", path.display())),
+            Ok(None) => {}
+            Err(err) => append_desktop_log(&handle, "WARN", &format!("cache backup failed: {err}")),
+        }
+        thread::sleep(CACHE_SWEEP_INTERVAL);
+    });
+    if let Err(err) = spawned {
+        append_desktop_log(app, "ERROR", &format!("failed to start cache sweeper: {err}"));
+    }
+}
+
+#[tauri::command]
+fn read_cache_entry(app: AppHandle, key: String) -> Result<Option<Value>, String> {
+    app.state::<CacheStore>().get(&key, unix_millis())
+}
+
+/// `ttl_ms` overrides the default expiry for this entry.
+#[tauri::command]
+fn write_cache_entry(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    key: String,
+    value: String,
+    ttl_ms: Option<u64>,
+) -> Result<(), String> {
+    if ttl_ms == Some(0) {
+        return Err("Cache TTL must be positive".to_string());
+    }
+    let parsed_value: Value = serde_json::from_str(&value)
+        .map_err(|e| format!("Invalid cache payload JSON: {e}"))?;
+    let evicted = app.state::<CacheStore>().set(&key, &parsed_value, unix_millis(), ttl_ms)?;
+    notify_cache_change(&app, CacheChangeAction::Write, Some(key), None, Some(window.label()));
+    notify_cache_evictions(&app, evicted, Some(window.label()));
+    Ok(())
+}
+
+#[tauri::command]
+fn list_cache_entries(app: AppHandle, prefix: Option<String>) -> Result<Vec<CacheEntryInfo>, String> {
+    app.state::<CacheStore>().list(prefix.as_deref())
+}
+
+#[tauri::command]
+fn get_cache_stats(app: AppHandle) -> Result<CacheStats, String> {
+    app.state::<CacheStore>().stats()
+}
+
+#[tauri::command]
+fn delete_cache_entry(app: AppHandle, window: tauri::WebviewWindow, key: String) -> Result<bool, String> {
+    let deleted = app.state::<CacheStore>().delete(&key)?;
+    if deleted {
+        append_desktop_log(&app, "INFO", &format!("deleted cache entry {key}"));
+        notify_cache_change(&app, CacheChangeAction::Delete, Some(key), None, Some(window.label()));
+    }
+    Ok(deleted)
+}
+
+#[tauri::command]
+fn delete_cache_prefix(app: AppHandle, window: tauri::WebviewWindow, prefix: String) -> Result<usize, String> {
+    let deleted = app.state::<CacheStore>().delete_prefix(&prefix)?;
+    append_desktop_log(&app, "INFO", &format!("deleted {deleted} cache entries under {prefix}"));
+    if deleted > 0 {
+        notify_cache_change(&app, CacheChangeAction::Delete, None, Some(prefix), Some(window.label()));
+    }
+    Ok(deleted)
+}
+
+#[tauri::command]
+fn clear_cache(app: AppHandle, window: tauri::WebviewWindow) -> Result<usize, String> {
+    let deleted = app.state::<CacheStore>().clear()?;
+    append_desktop_log(&app, "INFO", &format!("cleared cache ({deleted} entries)"));
+    if deleted > 0 {
+        notify_cache_change(&app, CacheChangeAction::Delete, None, Some(String::new()), Some(window.label()));
+    }
+    Ok(deleted)
+}
+
+/// Registers interest in keys under any of `prefixes` and returns the event name matching
+/// changes are delivered on, in addition to the `cache-changed` broadcast. They are sent only
+/// to the calling window, which should listen with that window as the target.
+#[tauri::command]
+fn subscribe_cache_changes(
+    window: tauri::WebviewWindow,
+    subscriptions: tauri::State<'_, CacheSubscriptions>,
+    prefixes: Vec<String>,
+) -> Result<CacheSubscriptionTicket, String> {
+    if prefixes.is_empty() {
+        return Err("Cache subscription needs at least one prefix".to_string());
+    }
+    let id = subscriptions.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let event = format!("{CACHE_CHANGED_EVENT}/{id}");
+    subscriptions
+        .entries
+        .lock()
+        .map_err(|_| "Failed to lock cache subscriptions".to_string())?
+        .insert(
+            event.clone(),
+            CacheSubscription {
+                window: window.label().to_string(),
+                prefixes,
+            },
+        );
+    Ok(CacheSubscriptionTicket {
+        event,
+        window: window.label().to_string(),
+    })
+}
+
+/// Returns whether the subscription existed.
+#[tauri::command]
+fn unsubscribe_cache_changes(
+    subscriptions: tauri::State<'_, CacheSubscriptions>,
+    subscription: String,
+) -> Result<bool, String> {
+    Ok(subscriptions
+        .entries
+        .lock()
+        .map_err(|_| "Failed to lock cache subscriptions".to_string())?
+        .remove(&subscription)
+        .is_some())
+}
+
+fn logs_dir_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve app log dir: {e}"))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create app log dir {}: {e}", dir.display()))?;
+    Ok(dir)
+}
+
+fn sidecar_log_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(logs_dir_path(app)?.join(LOCAL_API_LOG_FILE))
+}
+
+fn desktop_log_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(logs_dir_path(app)?.join(DESKTOP_LOG_FILE))
+}
+
+fn secret_audit_log_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(logs_dir_path(app)?.join(SECRET_AUDIT_LOG_FILE))
+}
+
+fn append_desktop_log(app: &AppHandle, level: &str, message: &str) {
+    let Ok(path) = desktop_log_path(app) else {
+        return;
+    };
+
+    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) else {
+        return;
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let _ = writeln!(file, "[{timestamp}][{level}] {message}");
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn open_in_shell(arg: &str) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut cmd = Command::new("open");
+        cmd.arg(arg);
+        cmd
+    };
+
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut cmd = Command::new("explorer");
+        cmd.arg(arg);
+        cmd
+    };
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let mut command = {
+        let mut cmd = Command::new("xdg-open");
+        cmd.arg(arg);
+        cmd
+    };
+
+    command
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {}: {e}", arg))
+}
+
+fn open_path_in_shell(path: &Path) -> Result<(), String> {
+    open_in_shell(&path.to_string_lossy())
+}
+
+#[tauri::command]
+fn open_url(url: String) -> Result<(), String> {
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err("Only http/https URLs are allowed".to_string());
+    }
+    open_in_shell(&url)
+}
+
+fn open_logs_folder_impl(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = logs_dir_path(app)?;
+    open_path_in_shell(&dir)?;
+    Ok(dir)
+}
+
+fn open_sidecar_log_impl(app: &AppHandle) -> Result<PathBuf, String> {
+    let log_path = sidecar_log_path(app)?;
+    if !log_path.exists() {
+        File::create(&log_path)
+            .map_err(|e| format!("Failed to create sidecar log {}: {e}", log_path.display()))?;
+    }
+    open_path_in_shell(&log_path)?;
+    Ok(log_path)
+}
+
+#[tauri::command]
+fn open_logs_folder(app: AppHandle) -> Result<String, String> {
+    open_logs_folder_impl(&app).map(|path| path.display().to_string())
+}
+
+#[tauri::command]
+fn open_sidecar_log_file(app: AppHandle) -> Result<String, String> {
+    open_sidecar_log_impl(&app).map(|path| path.display().to_string())
+}
+
+#[tauri::command]
+fn open_settings_window_command(app: AppHandle) -> Result<(), String> {
+    open_settings_window(&app)
+}
+
+#[tauri::command]
+fn close_settings_window(app: AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window("settings") {
+        window.close().map_err(|e| format!("Failed to close settings window: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Fetch JSON from Polymarket Gamma API using native TLS (bypasses Cloudflare JA3 blocking).
+/// Called from frontend when browser CORS and sidecar Node.js TLS both fail.
+#[tauri::command]
+async fn fetch_polymarket(path: String, params: String) -> Result<String, String> {
+    let allowed = ["events", "markets", "tags"];
+    let segment = path.trim_start_matches('/');
+    if !allowed.iter().any(|a| segment.starts_with(a)) {
+        return Err("Invalid Polymarket path".into());
+    }
+    let url = format!("https://gamma-api.polymarket.com/{}?{}", segment, params);
+    let client = reqwest::Client::builder()
+        .use_native_tls()
+        .build()
+        .map_err(|e| format!("HTTP client error: {e}"))?;
+    let resp = client
+        .get(&url)
+        .header("Accept", "application/json")
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| format!("Polymarket fetch failed: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("Polymarket HTTP {}", resp.status()));
+    }
+    resp.text().await.map_err(|e| format!("Read body failed: {e}"))
+}
+
+fn open_settings_window(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window("settings") {
+        let _ = window.show();
+        window
+            .set_focus()
+            .map_err(|e| format!("Failed to focus settings window: {e}"))?;
+        return Ok(());
+    }
+
+    WebviewWindowBuilder::new(app, "settings", WebviewUrl::App("settings.html".into()))
+        .title("World Monitor Settings")
+        .inner_size(980.0, 760.0)
+        .min_inner_size(820.0, 620.0)
+        .resizable(true)
+        .build()
+        .map_err(|e| format!("Failed to create settings window: {e}"))?;
+
+    Ok(())
+}
+
+fn build_app_menu(handle: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    let settings_item = MenuItem::with_id(
+        handle,
+        MENU_FILE_SETTINGS_ID,
+        "Settings...",
+        true,
+        Some("CmdOrCtrl+,"),
+    )?;
+    let separator = PredefinedMenuItem::separator(handle)?;
+    let quit_item = PredefinedMenuItem::quit(handle, Some("Quit"))?;
+    let file_menu =
+        Submenu::with_items(handle, "File", true, &[&settings_item, &separator, &quit_item])?;
+
+    let about_metadata = AboutMetadata {
+        name: Some("World Monitor".into()),
+        version: Some(env!("CARGO_PKG_VERSION").into()),
+        copyright: Some("\u{00a9} 2025 Elie Habib".into()),
+        website: Some("https://worldmonitor.app".into()),
+        website_label: Some("worldmonitor.app".into()),
+        ..Default::default()
     };
     let about_item = PredefinedMenuItem::about(handle, Some("About World Monitor"), Some(about_metadata))?;
     let github_item = MenuItem::with_id(
@@ -366,207 +1812,1584 @@ fn build_app_menu(handle: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
         &[&about_item, &help_separator, &github_item, &devtools_item],
     )?;
 
-    Menu::with_items(handle, &[&file_menu, &help_menu])
+    Menu::with_items(handle, &[&file_menu, &help_menu])
+}
+
+fn handle_menu_event(app: &AppHandle, event: tauri::menu::MenuEvent) {
+    match event.id().as_ref() {
+        MENU_FILE_SETTINGS_ID => {
+            if let Err(err) = open_settings_window(app) {
+                append_desktop_log(app, "ERROR", &format!("settings menu failed: {err}"));
+                eprintln!("[tauri] settings menu failed: {err}");
+            }
+        }
+        MENU_HELP_GITHUB_ID => {
+            let _ = open_in_shell("https://github.com/koala73/worldmonitor");
+        }
+        MENU_HELP_DEVTOOLS_ID => {
+            if let Some(window) = app.get_webview_window("main") {
+                if window.is_devtools_open() {
+                    window.close_devtools();
+                } else {
+                    window.open_devtools();
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn local_api_paths(app: &AppHandle) -> (PathBuf, PathBuf) {
+    let resource_dir = app
+        .path()
+        .resource_dir()
+        .unwrap_or_else(|_| PathBuf::from("."));
+
+    let sidecar_script = if cfg!(debug_assertions) {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sidecar/local-api-server.mjs")
+    } else {
+        resource_dir.join("sidecar/local-api-server.mjs")
+    };
+
+    let api_dir_root = if cfg!(debug_assertions) {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("."))
+    } else {
+        let direct_api = resource_dir.join("api");
+        let lifted_root = resource_dir.join("_up_");
+        let lifted_api = lifted_root.join("api");
+        if direct_api.exists() {
+            resource_dir
+        } else if lifted_api.exists() {
+            lifted_root
+        } else {
+            resource_dir
+        }
+    };
+
+    (sidecar_script, api_dir_root)
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeCandidate {
+    path: String,
+    source: &'static str,
+    version: Option<String>,
+    accepted: bool,
+    reason: Option<String>,
+}
+
+struct NodeResolution {
+    binary: Option<PathBuf>,
+    version: Option<String>,
+    candidates: Vec<NodeCandidate>,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from)
+}
+
+fn env_dir_or_home(var: &str, home_relative: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(home_relative)))
+}
+
+fn parse_node_version(raw: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = raw.trim().trim_start_matches('v').splitn(3, '.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().and_then(|part| part.parse().ok()).unwrap_or(0);
+    let patch = parts
+        .next()
+        .and_then(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// Lists `<root>/<version>/<suffix>` for every installed version, newest first.
+fn versioned_node_binaries(root: Option<PathBuf>, suffix: &[&str]) -> Vec<PathBuf> {
+    let Some(root) = root else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&root) else {
+        return Vec::new();
+    };
+
+    let mut versions: Vec<((u32, u32, u32), PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let version = parse_node_version(name.trim_start_matches("node-"))?;
+            let binary = suffix.iter().fold(entry.path(), |path, part| path.join(part));
+            Some((version, binary))
+        })
+        .collect();
+    versions.sort_by_key(|(version, _)| std::cmp::Reverse(*version));
+    versions.into_iter().map(|(_, binary)| binary).collect()
+}
+
+/// Every location a Node binary may live, in priority order, tagged with where it came from.
+/// Desktop launchers don't inherit shell `PATH` tweaks, so version managers are searched directly.
+fn node_binary_locations() -> Vec<(&'static str, PathBuf)> {
+    let node_name = if cfg!(windows) { "node.exe" } else { "node" };
+    let bin_node: &[&str] = if cfg!(windows) { &[node_name] } else { &["bin", node_name] };
+    let mut locations: Vec<(&'static str, PathBuf)> = Vec::new();
+
+    if let Some(path_var) = env::var_os("PATH") {
+        for dir in env::split_paths(&path_var) {
+            locations.push(("PATH", dir.join(node_name)));
+        }
+    }
+
+    let nvm_root = env_dir_or_home("NVM_DIR", ".nvm").map(|dir| dir.join("versions").join("node"));
+    for binary in versioned_node_binaries(nvm_root, &["bin", node_name]) {
+        locations.push(("nvm", binary));
+    }
+    if cfg!(windows) {
+        let nvm_windows_root = env::var_os("NVM_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("nvm")));
+        for binary in versioned_node_binaries(nvm_windows_root, &[node_name]) {
+            locations.push(("nvm", binary));
+        }
+    }
+
+    let fnm_default_root = if cfg!(target_os = "macos") {
+        "Library/Application Support/fnm"
+    } else if cfg!(windows) {
+        "AppData/Roaming/fnm"
+    } else {
+        ".local/share/fnm"
+    };
+    for fnm_root in [
+        env_dir_or_home("FNM_DIR", fnm_default_root),
+        home_dir().map(|home| home.join(".fnm")),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        let default_alias = bin_node
+            .iter()
+            .fold(fnm_root.join("aliases").join("default"), |path, part| path.join(part));
+        locations.push(("fnm", default_alias));
+        let mut installation = vec!["installation"];
+        installation.extend_from_slice(bin_node);
+        for binary in versioned_node_binaries(Some(fnm_root.join("node-versions")), &installation) {
+            locations.push(("fnm", binary));
+        }
+    }
+
+    let volta_default_root = if cfg!(windows) { "AppData/Local/Volta" } else { ".volta" };
+    if let Some(volta_root) = env_dir_or_home("VOLTA_HOME", volta_default_root) {
+        locations.push(("volta", volta_root.join("bin").join(node_name)));
+    }
+
+    let asdf_root = env_dir_or_home("ASDF_DATA_DIR", ".asdf").map(|dir| dir.join("installs").join("nodejs"));
+    for binary in versioned_node_binaries(asdf_root, &["bin", node_name]) {
+        locations.push(("asdf", binary));
+    }
+
+    let common_locations = if cfg!(windows) {
+        vec![
+            PathBuf::from(r"C:\Program Files\nodejs\node.exe"),
+            PathBuf::from(r"C:\Program Files (x86)\nodejs\node.exe"),
+        ]
+    } else {
+        vec![
+            PathBuf::from("/opt/homebrew/bin/node"),
+            PathBuf::from("/usr/local/bin/node"),
+            PathBuf::from("/usr/bin/node"),
+            PathBuf::from("/opt/local/bin/node"),
+        ]
+    };
+    locations.extend(common_locations.into_iter().map(|path| ("system", path)));
+    locations
+}
+
+fn probe_node_version(binary: &Path) -> Result<String, String> {
+    let mut child = Command::new(binary)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to run --version: {e}"))?;
+    let Some(status) = wait_for_exit(&mut child, NODE_VERSION_PROBE_TIMEOUT) else {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err("--version timed out".to_string());
+    };
+    let mut stdout = String::new();
+    if let Some(mut pipe) = child.stdout.take() {
+        let _ = pipe.read_to_string(&mut stdout);
+    }
+    if !status.success() {
+        return Err(format!("--version failed ({status})"));
+    }
+    Ok(stdout.trim().to_string())
 }
 
-fn handle_menu_event(app: &AppHandle, event: tauri::menu::MenuEvent) {
-    match event.id().as_ref() {
-        MENU_FILE_SETTINGS_ID => {
-            if let Err(err) = open_settings_window(app) {
-                append_desktop_log(app, "ERROR", &format!("settings menu failed: {err}"));
-                eprintln!("[tauri] settings menu failed: {err}");
+fn evaluate_node_candidate(source: &'static str, path: &Path) -> NodeCandidate {
+    let mut candidate = NodeCandidate {
+        path: path.display().to_string(),
+        source,
+        version: None,
+        accepted: false,
+        reason: None,
+    };
+    match probe_node_version(path) {
+        Ok(version) => {
+            match parse_node_version(&version) {
+                Some((major, _, _)) if major >= MIN_NODE_MAJOR_VERSION => candidate.accepted = true,
+                Some(_) => {
+                    candidate.reason = Some(format!("Node {MIN_NODE_MAJOR_VERSION}+ required"))
+                }
+                None => candidate.reason = Some(format!("unrecognised version output {version:?}")),
             }
+            candidate.version = Some(version);
         }
-        MENU_HELP_GITHUB_ID => {
-            let _ = open_in_shell("https://github.com/koala73/worldmonitor");
+        Err(reason) => candidate.reason = Some(reason),
+    }
+    candidate
+}
+
+/// Finds the first Node binary that runs and meets the minimum version, recording every
+/// candidate looked at so failures can explain themselves.
+fn resolve_node_binary() -> NodeResolution {
+    let mut resolution = NodeResolution {
+        binary: None,
+        version: None,
+        candidates: Vec::new(),
+    };
+
+    if let Ok(explicit) = env::var("LOCAL_API_NODE_BIN") {
+        let explicit_path = PathBuf::from(explicit);
+        if !explicit_path.exists() {
+            resolution.candidates.push(NodeCandidate {
+                path: explicit_path.display().to_string(),
+                source: "LOCAL_API_NODE_BIN",
+                version: None,
+                accepted: false,
+                reason: Some("file does not exist".to_string()),
+            });
+        } else {
+            let candidate = evaluate_node_candidate("LOCAL_API_NODE_BIN", &explicit_path);
+            let accepted = candidate.accepted;
+            resolution.version = candidate.version.clone().filter(|_| accepted);
+            resolution.candidates.push(candidate);
+            if accepted {
+                resolution.binary = Some(explicit_path);
+                return resolution;
+            }
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    for (source, path) in node_binary_locations() {
+        if !path.is_file() || !seen.insert(fs::canonicalize(&path).unwrap_or_else(|_| path.clone())) {
+            continue;
+        }
+        let candidate = evaluate_node_candidate(source, &path);
+        let accepted = candidate.accepted;
+        let version = candidate.version.clone();
+        resolution.candidates.push(candidate);
+        if accepted {
+            resolution.binary = Some(path);
+            resolution.version = version;
+            return resolution;
+        }
+    }
+
+    resolution
+}
+
+fn describe_node_candidates(candidates: &[NodeCandidate]) -> String {
+    if candidates.is_empty() {
+        return "no node executable found".to_string();
+    }
+    candidates
+        .iter()
+        .map(|candidate| {
+            format!(
+                "{} [{}] {}{}",
+                candidate.path,
+                candidate.source,
+                candidate.version.as_deref().unwrap_or("version unknown"),
+                candidate
+                    .reason
+                    .as_deref()
+                    .map(|reason| format!(" - {reason}"))
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Matches `name` against an allowlist entry; a trailing `*` makes the entry a prefix.
+/// Windows variable names are case-insensitive, so they are compared that way there.
+fn env_name_matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = if cfg!(windows) {
+        (pattern.to_ascii_uppercase(), name.to_ascii_uppercase())
+    } else {
+        (pattern.to_string(), name.to_string())
+    };
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+/// Extra variables to inherit, from `LOCAL_API_ENV_PASSTHROUGH` (comma or whitespace separated).
+fn local_api_env_passthrough() -> Vec<String> {
+    env::var("LOCAL_API_ENV_PASSTHROUGH")
+        .map(|value| {
+            value
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The sidecar starts from an empty environment plus the allowlist and user pass-through, so
+/// credentials or `NODE_OPTIONS` in the launching shell don't leak into API handlers.
+/// `LOCAL_API_ENV_POLICY=inherit` restores the old inherit-everything behaviour.
+fn apply_local_api_env_policy(app: &AppHandle, cmd: &mut Command) {
+    if env::var("LOCAL_API_ENV_POLICY").is_ok_and(|policy| policy.trim() == "inherit") {
+        append_desktop_log(app, "WARN", "sidecar env policy=inherit; passing the full parent environment");
+        return;
+    }
+
+    let passthrough = local_api_env_passthrough();
+    let mut inherited = Vec::new();
+    cmd.env_clear();
+    for (name, value) in env::vars_os() {
+        let Some(name_str) = name.to_str() else {
+            continue;
+        };
+        let allowed = LOCAL_API_ENV_ALLOWLIST
+            .iter()
+            .copied()
+            .chain(passthrough.iter().map(String::as_str))
+            .any(|pattern| env_name_matches(pattern, name_str));
+        if allowed {
+            inherited.push(name_str.to_string());
+            cmd.env(&name, value);
+        }
+    }
+    inherited.sort();
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!(
+            "sidecar env policy=minimal inherited={} passthrough=[{}]",
+            inherited.join(","),
+            passthrough.join(",")
+        ),
+    );
+}
+
+/// Honours an explicit `LOCAL_API_PORT`, otherwise asks the OS for a free loopback port.
+/// The port stays fixed for the session because the webview CSP is generated from it.
+fn resolve_local_api_port() -> Result<u16, String> {
+    match env::var("LOCAL_API_PORT") {
+        Ok(configured) => parse_local_api_port(&configured),
+        Err(_) => allocate_loopback_port(),
+    }
+}
+
+fn parse_local_api_port(configured: &str) -> Result<u16, String> {
+    let configured = configured.trim();
+    match configured.parse::<u16>() {
+        Ok(port) if port != 0 => Ok(port),
+        _ => Err(format!("Invalid LOCAL_API_PORT: {configured}")),
+    }
+}
+
+fn allocate_loopback_port() -> Result<u16, String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .map_err(|e| format!("Failed to allocate a loopback port: {e}"))?;
+    listener
+        .local_addr()
+        .map(|addr| addr.port())
+        .map_err(|e| format!("Failed to read allocated port: {e}"))
+}
+
+fn rewrite_csp_port(csp: &mut Option<Csp>, port: u16) {
+    let Some(policy) = csp.as_ref() else {
+        return;
+    };
+    let rewritten = policy.to_string().replace(
+        &format!("127.0.0.1:{DEFAULT_LOCAL_API_PORT}"),
+        &format!("127.0.0.1:{port}"),
+    );
+    *csp = Some(Csp::Policy(rewritten));
+}
+
+fn apply_local_api_port_to_csp(config: &mut tauri::Config, port: u16) {
+    if port == DEFAULT_LOCAL_API_PORT {
+        return;
+    }
+    rewrite_csp_port(&mut config.app.security.csp, port);
+    rewrite_csp_port(&mut config.app.security.dev_csp, port);
+}
+
+fn local_api_status_snapshot(app: &AppHandle) -> LocalApiStatus {
+    let state = app.state::<LocalApiState>();
+    let mut snapshot = state
+        .status
+        .lock()
+        .map(|status| status.clone())
+        .unwrap_or_default();
+    snapshot.port = state.port;
+    if matches!(snapshot.phase, LocalApiPhase::Starting | LocalApiPhase::Ready) {
+        snapshot.uptime_ms = local_api_uptime(app).map(|uptime| uptime.as_millis() as u64);
+    }
+    snapshot
+}
+
+/// Moves the sidecar status to `phase`, applies `update`, and pushes the result to every webview.
+fn record_local_api_event(
+    app: &AppHandle,
+    phase: LocalApiPhase,
+    update: impl FnOnce(&mut LocalApiStatus),
+) {
+    if let Ok(mut status) = app.state::<LocalApiState>().status.lock() {
+        status.phase = phase;
+        update(&mut status);
+    }
+    let snapshot = local_api_status_snapshot(app);
+    if let Err(err) = app.emit(LOCAL_API_STATUS_EVENT, snapshot) {
+        append_desktop_log(app, "WARN", &format!("failed to emit {LOCAL_API_STATUS_EVENT}: {err}"));
+    }
+}
+
+fn local_api_phase(app: &AppHandle) -> LocalApiPhase {
+    app.state::<LocalApiState>()
+        .status
+        .lock()
+        .map(|status| status.phase)
+        .unwrap_or_default()
+}
+
+fn start_local_api(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<LocalApiState>();
+    let mut slot = state
+        .child
+        .lock()
+        .map_err(|_| "Failed to lock local API state".to_string())?;
+    start_local_api_locked(app, &mut slot)
+}
+
+/// Launches into `slot`, which the caller holds locked so nobody else sees it empty meanwhile.
+fn start_local_api_locked(app: &AppHandle, slot: &mut Option<Child>) -> Result<(), String> {
+    launch_local_api(app, slot).inspect_err(|err| {
+        // Node discovery failures are reported with their own phase; everything else is generic.
+        let already_reported = app
+            .state::<LocalApiState>()
+            .status
+            .lock()
+            .is_ok_and(|status| {
+                status.phase == LocalApiPhase::NodeMissing
+                    && status.last_error.as_deref() == Some(err.as_str())
+            });
+        if !already_reported {
+            record_local_api_event(app, LocalApiPhase::Failed, |status| {
+                status.pid = None;
+                status.last_error = Some(err.clone());
+            });
+        }
+    })
+}
+
+fn launch_local_api(app: &AppHandle, slot: &mut Option<Child>) -> Result<(), String> {
+    let state = app.state::<LocalApiState>();
+    if slot.is_some() {
+        return Ok(());
+    }
+    // Checked under the child lock so a concurrent stop_local_api can't miss a fresh spawn.
+    if state.shutting_down.load(Ordering::SeqCst) {
+        return Err("Desktop shell is shutting down".to_string());
+    }
+
+    let (script, resource_root) = local_api_paths(app);
+    if !script.exists() {
+        return Err(format!(
+            "Local API sidecar script missing at {}",
+            script.display()
+        ));
+    }
+    let node = resolve_node_binary();
+    for candidate in &node.candidates {
+        append_desktop_log(
+            app,
+            "INFO",
+            &format!(
+                "node candidate path={} source={} version={} accepted={}{}",
+                candidate.path,
+                candidate.source,
+                candidate.version.as_deref().unwrap_or("-"),
+                candidate.accepted,
+                candidate
+                    .reason
+                    .as_deref()
+                    .map(|reason| format!(" reason={reason}"))
+                    .unwrap_or_default()
+            ),
+        );
+    }
+    let Some(node_binary) = node.binary else {
+        let message = format!(
+            "Node.js {MIN_NODE_MAJOR_VERSION}+ executable not found ({}). Install Node {MIN_NODE_MAJOR_VERSION}+ or set LOCAL_API_NODE_BIN",
+            describe_node_candidates(&node.candidates)
+        );
+        record_local_api_event(app, LocalApiPhase::NodeMissing, |status| {
+            status.pid = None;
+            status.node_version = None;
+            status.node_candidates = node.candidates.clone();
+            status.last_error = Some(message.clone());
+        });
+        return Err(message);
+    };
+
+    let log_path = sidecar_log_path(app)?;
+    let log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map_err(|e| format!("Failed to open local API log {}: {e}", log_path.display()))?;
+    let log_file_err = log_file
+        .try_clone()
+        .map_err(|e| format!("Failed to clone local API log handle: {e}"))?;
+
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!(
+            "starting local API sidecar script={} resource_root={} port={} log={}",
+            script.display(),
+            resource_root.display(),
+            state.port,
+            log_path.display()
+        ),
+    );
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!(
+            "resolved node binary={} version={}",
+            node_binary.display(),
+            node.version.as_deref().unwrap_or("-")
+        ),
+    );
+
+    // Fresh token per launch (prevents other local processes from accessing sidecar); a restart
+    // invalidates every token handed out for the previous sidecar, including per-window ones.
+    let local_api_token = generate_local_token()?;
+    {
+        // This launch reads every secret from its sources, so earlier pending pushes are moot.
+        let mut pending = state
+            .pending_secret_sync
+            .lock()
+            .map_err(|_| "Failed to lock pending secret sync")?;
+        pending.clear();
+        state.identity_verified.store(false, Ordering::SeqCst);
+    }
+    *state.token.lock().map_err(|_| "Failed to lock token slot")? = Some(local_api_token.clone());
+
+    let mut cmd = Command::new(&node_binary);
+    apply_local_api_env_policy(app, &mut cmd);
+    cmd.arg(&script)
+        .env("LOCAL_API_PORT", state.port.to_string())
+        .env("LOCAL_API_RESOURCE_DIR", resource_root)
+        .env("LOCAL_API_MODE", "tauri-sidecar")
+        .env("LOCAL_API_TOKEN", &local_api_token)
+        .env("LOCAL_API_SECRET_KEYS", secrets::secret_ids().join(","))
+        .stdout(Stdio::from(log_file))
+        .stderr(Stdio::from(log_file_err));
+
+    // Lead a fresh process group so shutdown can signal Node and anything it spawned.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+    }
+
+    // Pass secrets to sidecar as env vars, each from its highest-precedence source
+    let mut injected: BTreeMap<&str, u32> = BTreeMap::new();
+    for key in secrets::secret_ids() {
+        if let Ok(Some(resolved)) = resolve_secret(app, key) {
+            cmd.env(key, &resolved.value);
+            *injected.entry(resolved.source.as_str()).or_default() += 1;
+        }
+    }
+    let secret_count: u32 = injected.values().sum();
+    let by_source = injected
+        .iter()
+        .map(|(source, count)| format!("{source}={count}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!("injected {secret_count} secrets into sidecar env ({by_source})"),
+    );
+
+    let child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to launch local API: {e}"))?;
+    let pid = child.id();
+    append_desktop_log(app, "INFO", &format!("local API sidecar started pid={pid}"));
+    *slot = Some(child);
+    write_local_api_pidfile(app, pid, state.port, &local_api_token);
+    if let Ok(mut started_at) = state.started_at.lock() {
+        *started_at = Some(Instant::now());
+    }
+    record_local_api_event(app, LocalApiPhase::Starting, |status| {
+        status.pid = Some(pid);
+        status.node_version = node.version.clone();
+        status.node_candidates = node.candidates.clone();
+        status.exit_code = None;
+        status.restart_delay_ms = None;
+    });
+    Ok(())
+}
+
+enum LocalApiHealth {
+    Healthy,
+    Starting,
+    Unresponsive(String),
+    Exited(ExitStatus),
+    NotRunning,
+    Impostor(String),
+}
+
+enum IdentityCheck {
+    Verified,
+    Unreachable(String),
+    Mismatch(String),
+}
+
+/// Challenges whatever is listening on the local API port to prove it is the child we spawned:
+/// it must report our child's pid and sign a fresh nonce with the token only that child was given.
+/// Runs before the token is sent anywhere, so an impostor never sees it.
+fn verify_local_api_identity(
+    client: &reqwest::blocking::Client,
+    port: u16,
+    token: &str,
+    expected_pid: u32,
+) -> IdentityCheck {
+    challenge_local_api(client, port, expected_pid, "proof", token)
+}
+
+/// Same challenge, answered with the proof keyed by the token's SHA-256, which is all a pidfile
+/// from a previous session holds.
+fn verify_stale_local_api_identity(
+    client: &reqwest::blocking::Client,
+    port: u16,
+    token_hash: &str,
+    expected_pid: u32,
+) -> IdentityCheck {
+    challenge_local_api(client, port, expected_pid, "pidfileProof", token_hash)
+}
+
+fn challenge_local_api(
+    client: &reqwest::blocking::Client,
+    port: u16,
+    expected_pid: u32,
+    proof_field: &str,
+    key: &str,
+) -> IdentityCheck {
+    let nonce = match generate_local_token() {
+        Ok(nonce) => nonce,
+        Err(err) => return IdentityCheck::Unreachable(err),
+    };
+    let response = match client
+        .get(format!("http://127.0.0.1:{port}/api/local-identity"))
+        .query(&[("nonce", &nonce)])
+        .send()
+    {
+        Ok(response) => response,
+        Err(err) if err.is_connect() || err.is_timeout() => {
+            return IdentityCheck::Unreachable(format!("/api/local-identity unreachable: {err}"));
+        }
+        Err(err) => return IdentityCheck::Mismatch(format!("/api/local-identity failed: {err}")),
+    };
+    if !response.status().is_success() {
+        return IdentityCheck::Mismatch(format!(
+            "/api/local-identity returned HTTP {}",
+            response.status()
+        ));
+    }
+    let body: Value = match response.json() {
+        Ok(body) => body,
+        Err(err) => return IdentityCheck::Mismatch(format!("/api/local-identity returned invalid JSON: {err}")),
+    };
+
+    let reported_pid = body.get("pid").and_then(Value::as_u64);
+    if reported_pid != Some(u64::from(expected_pid)) {
+        return IdentityCheck::Mismatch(format!(
+            "listener reports pid {} but the sidecar we launched is pid {expected_pid}",
+            reported_pid.map_or_else(|| "?".to_string(), |pid| pid.to_string())
+        ));
+    }
+    let expected_proof = hmac_sha256_hex(key, &format!("wm-identity.{nonce}.{expected_pid}"));
+    if body.get(proof_field).and_then(Value::as_str) != Some(expected_proof.as_str()) {
+        return IdentityCheck::Mismatch("listener failed the token challenge".to_string());
+    }
+    IdentityCheck::Verified
+}
+
+fn probe_local_api(app: &AppHandle, client: &reqwest::blocking::Client) -> LocalApiHealth {
+    let state = app.state::<LocalApiState>();
+    let pid = {
+        let Ok(mut slot) = state.child.lock() else {
+            return LocalApiHealth::Unresponsive("local API state lock poisoned".to_string());
+        };
+        let Some(child) = slot.as_mut() else {
+            return LocalApiHealth::NotRunning;
+        };
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                slot.take();
+                return LocalApiHealth::Exited(status);
+            }
+            Ok(None) => child.id(),
+            Err(err) => {
+                return LocalApiHealth::Unresponsive(format!("failed to poll sidecar process: {err}"));
+            }
+        }
+    };
+
+    let in_startup_grace = state
+        .started_at
+        .lock()
+        .ok()
+        .and_then(|started_at| *started_at)
+        .is_some_and(|started_at| started_at.elapsed() < LOCAL_API_STARTUP_GRACE);
+    let Some(token) = state.token.lock().ok().and_then(|token| token.clone()) else {
+        return LocalApiHealth::Unresponsive("local API token missing".to_string());
+    };
+
+    let failure = if state.identity_verified.load(Ordering::SeqCst) {
+        let request = client
+            .get(format!("http://127.0.0.1:{}/api/local-status", state.port))
+            .bearer_auth(token);
+        match request.send() {
+            Ok(response) if response.status().is_success() => return LocalApiHealth::Healthy,
+            Ok(response) => format!("/api/local-status returned HTTP {}", response.status()),
+            Err(err) => format!("/api/local-status unreachable: {err}"),
+        }
+    } else {
+        match verify_local_api_identity(client, state.port, &token, pid) {
+            IdentityCheck::Verified => {
+                let pending = {
+                    let mut pending = state.pending_secret_sync.lock().ok();
+                    state.identity_verified.store(true, Ordering::SeqCst);
+                    pending.as_deref_mut().map(std::mem::take).unwrap_or_default()
+                };
+                append_desktop_log(app, "INFO", &format!("verified local API sidecar identity pid={pid}"));
+                if !pending.is_empty() {
+                    let keys: Vec<&str> = pending.iter().map(String::as_str).collect();
+                    sync_secrets_with_local_api(app, &keys);
+                }
+                return LocalApiHealth::Healthy;
+            }
+            IdentityCheck::Mismatch(reason) => {
+                return LocalApiHealth::Impostor(format!(
+                    "port {} is not served by our sidecar: {reason}",
+                    state.port
+                ));
+            }
+            IdentityCheck::Unreachable(reason) => reason,
+        }
+    };
+
+    if in_startup_grace {
+        LocalApiHealth::Starting
+    } else {
+        LocalApiHealth::Unresponsive(failure)
+    }
+}
+
+fn local_api_uptime(app: &AppHandle) -> Option<Duration> {
+    let state = app.state::<LocalApiState>();
+    let started_at = state.started_at.lock().ok().and_then(|started_at| *started_at);
+    started_at.map(|started_at| started_at.elapsed())
+}
+
+fn restart_backoff(attempt: u32) -> Duration {
+    LOCAL_API_RESTART_BACKOFF_INITIAL
+        .saturating_mul(1u32 << attempt.min(6))
+        .min(LOCAL_API_RESTART_BACKOFF_MAX)
+}
+
+/// Sleeps for `duration`, waking early (and returning false) if shutdown begins.
+fn sleep_unless_shutdown(state: &LocalApiState, duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    while !state.shutting_down.load(Ordering::SeqCst) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        thread::sleep(remaining.min(Duration::from_millis(250)));
+    }
+    false
+}
+
+#[cfg(unix)]
+fn signal_process_group(pid: u32, signal: libc::c_int) -> bool {
+    // The sidecar leads its own process group (pgid == pid), so -pid reaches the whole tree.
+    unsafe { libc::kill(-(pid as libc::pid_t), signal) == 0 }
+}
+
+#[cfg(unix)]
+fn request_process_tree_exit(pid: u32) -> bool {
+    signal_process_group(pid, libc::SIGTERM)
+}
+
+#[cfg(unix)]
+fn force_process_tree_exit(pid: u32) {
+    signal_process_group(pid, libc::SIGKILL);
+}
+
+#[cfg(windows)]
+fn run_taskkill(pid: u32, force: bool) -> bool {
+    let mut cmd = Command::new("taskkill");
+    cmd.args(["/PID", &pid.to_string(), "/T"]);
+    if force {
+        cmd.arg("/F");
+    }
+    cmd.stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Without `/F`, taskkill asks each process to close its window. A console process such as Node
+/// has none, so this almost always fails and shutdown falls through to the forced tree kill.
+#[cfg(windows)]
+fn request_process_tree_exit(pid: u32) -> bool {
+    run_taskkill(pid, false)
+}
+
+#[cfg(windows)]
+fn force_process_tree_exit(pid: u32) {
+    run_taskkill(pid, true);
+}
+
+#[cfg(unix)]
+fn process_is_alive(pid: u32) -> bool {
+    // Signal 0 only checks the pid; EPERM still means something is running under it.
+    unsafe {
+        libc::kill(pid as libc::pid_t, 0) == 0
+            || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+}
+
+#[cfg(windows)]
+fn process_is_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH", "/FO", "CSV"])
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{pid}\"")))
+}
+
+#[cfg(unix)]
+fn process_command_line(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "command=", "-p", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(windows)]
+fn process_command_line(pid: u32) -> Option<String> {
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            &format!("(Get-CimInstance Win32_Process -Filter 'ProcessId={pid}').CommandLine"),
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn wait_for_exit(child: &mut Child, timeout: Duration) -> Option<std::process::ExitStatus> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Some(status),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            _ => return None,
+        }
+    }
+}
+
+/// Polls `done` every 50ms until it holds or `timeout` passes; returns whether it held.
+fn wait_until(timeout: Duration, mut done: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if done() {
+            return true;
         }
-        MENU_HELP_DEVTOOLS_ID => {
-            if let Some(window) = app.get_webview_window("main") {
-                if window.is_devtools_open() {
-                    window.close_devtools();
-                } else {
-                    window.open_devtools();
-                }
-            }
+        if Instant::now() >= deadline {
+            return false;
         }
-        _ => {}
+        thread::sleep(Duration::from_millis(50));
     }
 }
 
-fn local_api_paths(app: &AppHandle) -> (PathBuf, PathBuf) {
-    let resource_dir = app
-        .path()
-        .resource_dir()
-        .unwrap_or_else(|_| PathBuf::from("."));
+/// Whether our child `pid` has exited, without reaping it. While the leader stays unreaped its
+/// pid, and with it the process group id, can't be reused, so signalling `-pid` stays safe.
+#[cfg(unix)]
+fn leader_has_exited(pid: u32) -> bool {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let rc = unsafe {
+        libc::waitid(
+            libc::P_PID,
+            pid as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+        )
+    };
+    // With WNOHANG the struct stays zeroed while the child is still running.
+    rc != 0 || info.si_signo != 0
+}
 
-    let sidecar_script = if cfg!(debug_assertions) {
-        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sidecar/local-api-server.mjs")
-    } else {
-        resource_dir.join("sidecar/local-api-server.mjs")
+/// Whether anything besides the (possibly zombie) leader is left in the sidecar's process group.
+/// If `pgrep` can't be run, members are assumed to remain so the caller escalates.
+#[cfg(unix)]
+fn group_has_other_members(pgid: u32) -> bool {
+    let Ok(output) = Command::new("pgrep")
+        .args(["-g", &pgid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+    else {
+        return true;
     };
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .any(|member| member != pgid.to_string())
+}
 
-    let api_dir_root = if cfg!(debug_assertions) {
-        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .parent()
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("."))
+/// Asks the sidecar's process group to exit and escalates to SIGKILL, reaping the child only
+/// after the last signal. The leader gets `grace` to exit; whatever it leaves behind in the group
+/// (grandchildren spawned by Node) gets a further `LOCAL_API_GROUP_GRACE` of its own before
+/// being killed. Returns a human-readable outcome for the log.
+#[cfg(unix)]
+fn shutdown_sidecar_child(mut child: Child, grace: Duration) -> String {
+    let pid = child.id();
+    let started = Instant::now();
+    let already_exited = leader_has_exited(pid);
+    let signalled = signal_process_group(pid, libc::SIGTERM);
+    let leader_exited = already_exited || (signalled && wait_until(grace, || leader_has_exited(pid)));
+    let mut outcome = if already_exited {
+        format!("pid={pid} had already exited")
+    } else if leader_exited {
+        format!("pid={pid} exited gracefully in {}ms", started.elapsed().as_millis())
+    } else if signalled {
+        format!("pid={pid} ignored shutdown for {}ms", grace.as_millis())
     } else {
-        let direct_api = resource_dir.join("api");
-        let lifted_root = resource_dir.join("_up_");
-        let lifted_api = lifted_root.join("api");
-        if direct_api.exists() {
-            resource_dir
-        } else if lifted_api.exists() {
-            lifted_root
-        } else {
-            resource_dir
+        format!("pid={pid} could not be signalled gracefully")
+    };
+
+    let group_exited =
+        leader_exited && wait_until(LOCAL_API_GROUP_GRACE, || !group_has_other_members(pid));
+    if !group_exited {
+        if leader_exited {
+            outcome.push_str(&format!(
+                "; child processes outlived it by {}ms",
+                LOCAL_API_GROUP_GRACE.as_millis()
+            ));
         }
+        signal_process_group(pid, libc::SIGKILL);
+        outcome.push_str("; force-killed process group");
+    }
+    match child.wait() {
+        Ok(status) => format!("{outcome} ({status})"),
+        Err(err) => format!("{outcome}; could not be reaped: {err}"),
+    }
+}
+
+/// Windows has no process groups to signal. The graceful `taskkill /T` is tried, but it almost
+/// always fails for a console Node process (see `request_process_tree_exit`), so the usual
+/// outcome is a forced tree kill. The tree is killed while the child handle is still open,
+/// which keeps the pid from being reused.
+#[cfg(windows)]
+fn shutdown_sidecar_child(mut child: Child, grace: Duration) -> String {
+    let pid = child.id();
+    if let Ok(Some(status)) = child.try_wait() {
+        force_process_tree_exit(pid);
+        return format!("pid={pid} had already exited ({status})");
+    }
+
+    let started = Instant::now();
+    let outcome = if !request_process_tree_exit(pid) {
+        format!("pid={pid} has no window to close (console process), so graceful shutdown is unavailable")
+    } else if let Some(status) = wait_for_exit(&mut child, grace) {
+        force_process_tree_exit(pid);
+        return format!(
+            "pid={pid} exited gracefully in {}ms ({status})",
+            started.elapsed().as_millis()
+        );
+    } else {
+        format!("pid={pid} ignored shutdown for {}ms", grace.as_millis())
     };
 
-    (sidecar_script, api_dir_root)
+    force_process_tree_exit(pid);
+    let _ = child.kill();
+    match child.wait() {
+        Ok(status) => format!("{outcome}; force-killed ({status})"),
+        Err(err) => format!("{outcome}; force-kill could not be reaped: {err}"),
+    }
 }
 
-fn resolve_node_binary() -> Option<PathBuf> {
-    if let Ok(explicit) = env::var("LOCAL_API_NODE_BIN") {
-        let explicit_path = PathBuf::from(explicit);
-        if explicit_path.exists() {
-            return Some(explicit_path);
-        }
+fn terminate_local_api_child(app: &AppHandle) {
+    let Some(state) = app.try_state::<LocalApiState>() else {
+        return;
+    };
+    let child = state.child.lock().ok().and_then(|mut slot| slot.take());
+    if let Some(child) = child {
+        let pid = child.id();
+        let outcome = shutdown_sidecar_child(child, LOCAL_API_SHUTDOWN_GRACE);
+        remove_local_api_pidfile(app, pid);
+        append_desktop_log(app, "INFO", &format!("local API sidecar stopped: {outcome}"));
     }
+}
 
-    let node_name = if cfg!(windows) { "node.exe" } else { "node" };
-    if let Some(path_var) = env::var_os("PATH") {
-        for dir in env::split_paths(&path_var) {
-            let candidate = dir.join(node_name);
-            if candidate.exists() {
-                return Some(candidate);
-            }
+/// Written next to the app data on every launch so a shell that died without stopping its
+/// sidecar can find and clean up the orphan the next time it starts. Keyed by port, so instances
+/// running side by side on different ports each keep their own record.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LocalApiPidfile {
+    pid: u32,
+    shell_pid: u32,
+    port: u16,
+    token_hash: String,
+    started_at: u64,
+}
+
+fn local_api_pidfile_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create app data directory {}: {e}", dir.display()))?;
+    Ok(dir)
+}
+
+fn local_api_pidfile_path(app: &AppHandle, port: u16) -> Result<PathBuf, String> {
+    Ok(local_api_pidfile_dir(app)?.join(format!("{LOCAL_API_PID_FILE_PREFIX}{port}{LOCAL_API_PID_FILE_SUFFIX}")))
+}
+
+fn read_local_api_pidfile(path: &Path) -> Option<LocalApiPidfile> {
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+impl LocalApiPidfile {
+    /// Only a hash of the token is recorded, enough to key the identity challenge.
+    fn new(pid: u32, port: u16, token: &str) -> Self {
+        Self {
+            pid,
+            shell_pid: std::process::id(),
+            port,
+            token_hash: to_hex(&Sha256::digest(token.as_bytes())),
+            started_at: unix_millis(),
         }
     }
+}
 
-    let common_locations = if cfg!(windows) {
-        vec![
-            PathBuf::from(r"C:\Program Files\nodejs\node.exe"),
-            PathBuf::from(r"C:\Program Files (x86)\nodejs\node.exe"),
-        ]
-    } else {
-        vec![
-            PathBuf::from("/opt/homebrew/bin/node"),
-            PathBuf::from("/usr/local/bin/node"),
-            PathBuf::from("/usr/bin/node"),
-            PathBuf::from("/opt/local/bin/node"),
-        ]
-    };
+fn write_local_api_pidfile_at(path: &Path, record: &LocalApiPidfile) -> Result<(), String> {
+    let data = serde_json::to_string(record).map_err(|e| format!("Failed to serialize pidfile: {e}"))?;
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
 
-    common_locations.into_iter().find(|path| path.exists())
+fn write_local_api_pidfile(app: &AppHandle, pid: u32, port: u16, token: &str) {
+    let record = LocalApiPidfile::new(pid, port, token);
+    let result = local_api_pidfile_path(app, port).and_then(|path| write_local_api_pidfile_at(&path, &record));
+    if let Err(err) = result {
+        append_desktop_log(app, "WARN", &format!("local API pidfile not written: {err}"));
+    }
 }
 
-fn start_local_api(app: &AppHandle) -> Result<(), String> {
-    let state = app.state::<LocalApiState>();
-    let mut slot = state
-        .child
-        .lock()
-        .map_err(|_| "Failed to lock local API state".to_string())?;
-    if slot.is_some() {
-        return Ok(());
+/// Removes the pidfile only if it still describes `pid`; a sibling instance may have replaced it.
+fn remove_local_api_pidfile(app: &AppHandle, pid: u32) {
+    let port = app.state::<LocalApiState>().port;
+    let Ok(path) = local_api_pidfile_path(app, port) else {
+        return;
+    };
+    if read_local_api_pidfile(&path).is_some_and(|record| record.pid == pid) {
+        let _ = fs::remove_file(&path);
     }
+}
 
-    let (script, resource_root) = local_api_paths(app);
-    if !script.exists() {
-        return Err(format!(
-            "Local API sidecar script missing at {}",
-            script.display()
-        ));
+/// Terminates sidecars left running by previous shells that crashed or were killed. Every
+/// per-port pidfile is checked, since the port may differ between sessions.
+fn reap_stale_local_api(app: &AppHandle) {
+    let Ok(dir) = local_api_pidfile_dir(app) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return;
+    };
+    let client = reqwest::blocking::Client::builder()
+        .timeout(LOCAL_API_HEALTH_TIMEOUT)
+        .build()
+        .ok();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(LOCAL_API_PID_FILE_PREFIX) && name.ends_with(LOCAL_API_PID_FILE_SUFFIX) {
+            reap_stale_local_api_pidfile(app, &entry.path(), client.as_ref());
+        }
     }
-    let node_binary = resolve_node_binary().ok_or_else(|| {
-        "Node.js executable not found. Install Node 18+ or set LOCAL_API_NODE_BIN".to_string()
-    })?;
+}
 
-    let log_path = sidecar_log_path(app)?;
-    let log_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)
-        .map_err(|e| format!("Failed to open local API log {}: {e}", log_path.display()))?;
-    let log_file_err = log_file
-        .try_clone()
-        .map_err(|e| format!("Failed to clone local API log handle: {e}"))?;
+/// The record of a pidfile whose sidecar pid is still running. Unreadable or garbage pidfiles,
+/// and those whose process has exited, are deleted.
+fn live_local_api_pidfile(path: &Path) -> Option<LocalApiPidfile> {
+    match read_local_api_pidfile(path) {
+        Some(record) if process_is_alive(record.pid) => Some(record),
+        _ => {
+            let _ = fs::remove_file(path);
+            None
+        }
+    }
+}
+
+/// A recorded process is only touched if its shell is gone, its command line is our sidecar
+/// script, and it doesn't fail the identity challenge keyed by the recorded token hash, so a
+/// reused pid or the sidecar of another running instance is left alone. A survivor too wedged
+/// to answer the challenge is judged on its command line alone.
+fn reap_stale_local_api_pidfile(app: &AppHandle, path: &Path, client: Option<&reqwest::blocking::Client>) {
+    let Some(record) = live_local_api_pidfile(path) else {
+        return;
+    };
+    if record.shell_pid != std::process::id() && process_is_alive(record.shell_pid) {
+        append_desktop_log(
+            app,
+            "INFO",
+            &format!(
+                "local API pidfile belongs to running shell pid={}; leaving sidecar pid={} alone",
+                record.shell_pid, record.pid
+            ),
+        );
+        return;
+    }
+    let (script, _) = local_api_paths(app);
+    let script_name = script.file_name().and_then(|name| name.to_str()).unwrap_or("local-api-server.mjs");
+    let command_line = process_command_line(record.pid).unwrap_or_default();
+    if !command_line.contains(script_name) {
+        append_desktop_log(
+            app,
+            "INFO",
+            &format!("pid={} from local API pidfile is not our sidecar; discarding pidfile", record.pid),
+        );
+        let _ = fs::remove_file(path);
+        return;
+    }
+    let identity = match client {
+        Some(client) => verify_stale_local_api_identity(client, record.port, &record.token_hash, record.pid),
+        None => IdentityCheck::Unreachable("no HTTP client".to_string()),
+    };
+    match identity {
+        IdentityCheck::Verified => {}
+        IdentityCheck::Mismatch(reason) => {
+            append_desktop_log(
+                app,
+                "INFO",
+                &format!(
+                    "pid={} from local API pidfile failed the identity check ({reason}); leaving it alone",
+                    record.pid
+                ),
+            );
+            let _ = fs::remove_file(path);
+            return;
+        }
+        IdentityCheck::Unreachable(reason) => {
+            append_desktop_log(
+                app,
+                "WARN",
+                &format!(
+                    "stale local API sidecar pid={} did not answer the identity check ({reason}); \
+                     relying on its command line",
+                    record.pid
+                ),
+            );
+        }
+    }
 
     append_desktop_log(
         app,
-        "INFO",
+        "WARN",
         &format!(
-            "starting local API sidecar script={} resource_root={} log={}",
-            script.display(),
-            resource_root.display(),
-            log_path.display()
+            "found stale local API sidecar pid={} port={} from a previous session; terminating",
+            record.pid, record.port
         ),
     );
-    append_desktop_log(app, "INFO", &format!("resolved node binary={}", node_binary.display()));
-
-    // Generate a unique token for local API auth (prevents other local processes from accessing sidecar)
-    let mut token_slot = state.token.lock().map_err(|_| "Failed to lock token slot")?;
-    if token_slot.is_none() {
-        *token_slot = Some(generate_local_token());
+    request_process_tree_exit(record.pid);
+    let deadline = Instant::now() + LOCAL_API_SHUTDOWN_GRACE;
+    while process_is_alive(record.pid) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(100));
     }
-    let local_api_token = token_slot.clone().unwrap();
-    drop(token_slot);
+    let outcome = if process_is_alive(record.pid) {
+        force_process_tree_exit(record.pid);
+        "killed"
+    } else {
+        "exited"
+    };
+    append_desktop_log(app, "INFO", &format!("stale local API sidecar pid={} {outcome}", record.pid));
+    let _ = fs::remove_file(path);
+}
 
-    let mut cmd = Command::new(&node_binary);
-    cmd.arg(&script)
-        .env("LOCAL_API_PORT", LOCAL_API_PORT)
-        .env("LOCAL_API_RESOURCE_DIR", resource_root)
-        .env("LOCAL_API_MODE", "tauri-sidecar")
-        .env("LOCAL_API_TOKEN", &local_api_token)
-        .stdout(Stdio::from(log_file))
-        .stderr(Stdio::from(log_file_err));
+/// Watches the sidecar for the lifetime of the app: polls the child's exit status and
+/// `/api/local-status`, and restarts it with exponential backoff until it crash-loops. A first
+/// launch that failed is retried the same way.
+fn supervise_local_api(app: &AppHandle) {
+    let client = match reqwest::blocking::Client::builder()
+        .timeout(LOCAL_API_HEALTH_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            append_desktop_log(app, "ERROR", &format!("local API supervisor disabled: {err}"));
+            return;
+        }
+    };
 
-    // Pass keychain secrets to sidecar as env vars
-    let mut secret_count = 0u32;
-    for key in SUPPORTED_SECRET_KEYS.iter() {
-        if let Ok(entry) = Entry::new(KEYRING_SERVICE, key) {
-            if let Ok(value) = entry.get_password() {
-                if !value.trim().is_empty() {
-                    cmd.env(key, value.trim());
-                    secret_count += 1;
+    let state = app.state::<LocalApiState>();
+    let mut recent_restarts: VecDeque<Instant> = VecDeque::new();
+    let mut backoff_attempt = 0u32;
+    let mut restart_count = 0u32;
+    let mut health_failures = 0u32;
+
+    loop {
+        let interval = if local_api_phase(app) == LocalApiPhase::Starting {
+            LOCAL_API_READY_POLL_INTERVAL
+        } else {
+            LOCAL_API_HEALTH_INTERVAL
+        };
+        if !sleep_unless_shutdown(&state, interval) {
+            return;
+        }
+
+        let reason = match probe_local_api(app, &client) {
+            LocalApiHealth::Healthy => {
+                health_failures = 0;
+                if local_api_phase(app) != LocalApiPhase::Ready {
+                    record_local_api_event(app, LocalApiPhase::Ready, |_| {});
+                }
+                if local_api_uptime(app).is_some_and(|uptime| uptime >= LOCAL_API_STABLE_UPTIME) {
+                    backoff_attempt = 0;
+                }
+                continue;
+            }
+            LocalApiHealth::Starting => continue,
+            LocalApiHealth::Unresponsive(reason) => {
+                health_failures += 1;
+                append_desktop_log(
+                    app,
+                    "WARN",
+                    &format!(
+                        "local API health check failed ({health_failures}/{LOCAL_API_MAX_HEALTH_FAILURES}): {reason}"
+                    ),
+                );
+                if health_failures < LOCAL_API_MAX_HEALTH_FAILURES {
+                    continue;
                 }
+                format!("unresponsive after {health_failures} failed health checks")
+            }
+            LocalApiHealth::Exited(status) => {
+                let reason = format!("exited unexpectedly ({status})");
+                record_local_api_event(app, LocalApiPhase::Exited, |local_api| {
+                    local_api.pid = None;
+                    local_api.exit_code = status.code();
+                    local_api.last_error = Some(reason.clone());
+                });
+                reason
             }
+            LocalApiHealth::NotRunning => "not running".to_string(),
+            LocalApiHealth::Impostor(reason) => {
+                // Restarting on the same port would only hand the next token to the same listener.
+                let message = format!("local API sidecar identity check failed: {reason}");
+                append_desktop_log(app, "ERROR", &message);
+                eprintln!("[tauri] {message}");
+                terminate_local_api_child(app);
+                record_local_api_event(app, LocalApiPhase::Failed, |status| {
+                    status.pid = None;
+                    status.last_error = Some(message);
+                });
+                return;
+            }
+        };
+        health_failures = 0;
+
+        let now = Instant::now();
+        while recent_restarts
+            .front()
+            .is_some_and(|restarted_at| now.duration_since(*restarted_at) > LOCAL_API_CRASH_LOOP_WINDOW)
+        {
+            recent_restarts.pop_front();
+        }
+        if recent_restarts.len() >= LOCAL_API_CRASH_LOOP_LIMIT {
+            let message = format!(
+                "local API sidecar {reason}; giving up after {} restarts within {}s",
+                recent_restarts.len(),
+                LOCAL_API_CRASH_LOOP_WINDOW.as_secs()
+            );
+            append_desktop_log(app, "ERROR", &message);
+            eprintln!("[tauri] {message}");
+            terminate_local_api_child(app);
+            record_local_api_event(app, LocalApiPhase::Failed, |status| {
+                status.pid = None;
+                status.last_error = Some(message);
+            });
+            return;
+        }
+
+        let delay = restart_backoff(backoff_attempt);
+        backoff_attempt += 1;
+        restart_count += 1;
+        append_desktop_log(
+            app,
+            "WARN",
+            &format!(
+                "local API sidecar {reason}; restart #{restart_count} in {}ms",
+                delay.as_millis()
+            ),
+        );
+        terminate_local_api_child(app);
+        record_local_api_event(app, LocalApiPhase::RestartScheduled, |status| {
+            status.pid = None;
+            status.restart_count = restart_count;
+            status.restart_delay_ms = Some(delay.as_millis() as u64);
+            status.last_error = Some(reason);
+        });
+        if !sleep_unless_shutdown(&state, delay) {
+            return;
+        }
+
+        recent_restarts.push_back(Instant::now());
+        match start_local_api(app) {
+            Ok(()) => append_desktop_log(
+                app,
+                "INFO",
+                &format!("local API sidecar restart #{restart_count} succeeded"),
+            ),
+            Err(err) => append_desktop_log(
+                app,
+                "ERROR",
+                &format!("local API sidecar restart #{restart_count} failed: {err}"),
+            ),
         }
     }
-    append_desktop_log(app, "INFO", &format!("injected {secret_count} keychain secrets into sidecar env"));
+}
 
-    let child = cmd
-        .spawn()
-        .map_err(|e| format!("Failed to launch local API: {e}"))?;
-    append_desktop_log(app, "INFO", &format!("local API sidecar started pid={}", child.id()));
-    *slot = Some(child);
+fn push_secret_to_local_api(app: &AppHandle, key: &str, value: Option<&str>) -> Result<(), String> {
+    let state = app.state::<LocalApiState>();
+    let token = state
+        .token
+        .lock()
+        .map_err(|_| "Failed to lock local API token".to_string())?
+        .clone()
+        .ok_or_else(|| "Token not generated".to_string())?;
+    if !state.identity_verified.load(Ordering::SeqCst) {
+        return Err("sidecar identity not verified yet".to_string());
+    }
+    let client = reqwest::blocking::Client::builder()
+        .timeout(LOCAL_API_HEALTH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))?;
+    let response = client
+        .post(format!("http://127.0.0.1:{}/api/local-env-update", state.port))
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "key": key, "value": value }))
+        .send()
+        .map_err(|e| format!("/api/local-env-update unreachable: {e}"))?;
+    if !response.status().is_success() {
+        return Err(format!("/api/local-env-update returned HTTP {}", response.status()));
+    }
     Ok(())
 }
 
-fn stop_local_api(app: &AppHandle) {
-    if let Ok(state) = app.try_state::<LocalApiState>().ok_or(()) {
-        if let Ok(mut slot) = state.child.lock() {
-            if let Some(mut child) = slot.take() {
-                let _ = child.kill();
-                append_desktop_log(app, "INFO", "local API sidecar stopped");
+/// Stops the current sidecar and launches a fresh one so it re-reads every secret from the keyring.
+fn restart_local_api(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<LocalApiState>();
+    // Hold the slot through shutdown and relaunch; if the supervisor saw it empty in between it
+    // would schedule a second restart of its own.
+    let mut slot = state
+        .child
+        .lock()
+        .map_err(|_| "Failed to lock local API state".to_string())?;
+    if let Some(child) = slot.take() {
+        let pid = child.id();
+        let outcome = shutdown_sidecar_child(child, LOCAL_API_SHUTDOWN_GRACE);
+        remove_local_api_pidfile(app, pid);
+        append_desktop_log(app, "INFO", &format!("local API sidecar stopped for restart: {outcome}"));
+    }
+    start_local_api_locked(app, &mut slot)
+}
+
+/// Restarts the sidecar so it re-reads secrets, unless it isn't running (it reads them on launch
+/// anyway). A sidecar that is still starting isn't killed; every key is queued for a push once
+/// its identity is verified instead.
+fn restart_local_api_if_running(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<LocalApiState>();
+    let running = state.child.lock().is_ok_and(|slot| slot.is_some());
+    if !running {
+        return Ok(());
+    }
+    if let Ok(mut pending) = state.pending_secret_sync.lock() {
+        if !state.identity_verified.load(Ordering::SeqCst) {
+            pending.extend(secrets::secret_ids().into_iter().map(str::to_string));
+            append_desktop_log(app, "INFO", "sidecar still starting; queued a full secret resync instead of restarting");
+            return Ok(());
+        }
+    }
+    restart_local_api(app)
+}
+
+/// Keeps the running sidecar's environment in step after secrets change, pushing each key's
+/// effective value (an env or `.env` value still wins over the store). A sidecar that isn't
+/// running picks the value up on its next launch, and one still starting gets the keys queued
+/// until its identity is verified rather than being restarted. Pushes and restarts block, so
+/// every command that gets here is `async` and runs off the main thread.
+fn sync_secrets_with_local_api(app: &AppHandle, keys: &[&str]) {
+    let Some(state) = app.try_state::<LocalApiState>() else {
+        return;
+    };
+    if state.child.lock().map_or(true, |slot| slot.is_none()) {
+        return;
+    }
+    if let Ok(mut pending) = state.pending_secret_sync.lock() {
+        if !state.identity_verified.load(Ordering::SeqCst) {
+            pending.extend(keys.iter().map(|key| key.to_string()));
+            append_desktop_log(
+                app,
+                "INFO",
+                &format!("sidecar still starting; queued {} secret change(s) until it is verified", keys.len()),
+            );
+            return;
+        }
+    }
+
+    for key in keys {
+        let value = resolve_secret(app, key).ok().flatten().map(|resolved| resolved.value);
+        let action = if value.is_some() { "set" } else { "unset" };
+        match push_secret_to_local_api(app, key, value.as_deref()) {
+            Ok(()) => append_desktop_log(app, "INFO", &format!("pushed secret {action} key={key} to sidecar")),
+            Err(err) => {
+                // A restart re-reads every secret, so the rest of the batch needs no push.
+                append_desktop_log(
+                    app,
+                    "WARN",
+                    &format!("failed to push secret {action} key={key} to sidecar: {err}; restarting sidecar"),
+                );
+                if let Err(err) = restart_local_api(app) {
+                    append_desktop_log(app, "ERROR", &format!("local API sidecar restart failed: {err}"));
+                }
+                return;
             }
         }
     }
 }
 
+fn spawn_local_api_supervisor(app: &AppHandle) {
+    let handle = app.clone();
+    let spawned = thread::Builder::new()
+        .name("local-api-supervisor".into())
+        .spawn(move || supervise_local_api(&handle));
+    if let Err(err) = spawned {
+        append_desktop_log(app, "ERROR", &format!("failed to start local API supervisor: {err}"));
+    }
+}
+
+fn stop_local_api(app: &AppHandle) {
+    let Some(state) = app.try_state::<LocalApiState>() else {
+        return;
+    };
+    state.shutting_down.store(true, Ordering::SeqCst);
+    let was_running = state.child.lock().is_ok_and(|slot| slot.is_some());
+    terminate_local_api_child(app);
+    if was_running {
+        record_local_api_event(app, LocalApiPhase::Stopped, |status| status.pid = None);
+    }
+}
+
 fn main() {
+    let local_api_port = resolve_local_api_port().unwrap_or_else(|err| {
+        eprintln!("[tauri] {err}; using port {DEFAULT_LOCAL_API_PORT}");
+        DEFAULT_LOCAL_API_PORT
+    });
+    let mut context = tauri::generate_context!();
+    apply_local_api_port_to_csp(context.config_mut(), local_api_port);
+
     tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
         .menu(build_app_menu)
         .on_menu_event(handle_menu_event)
-        .manage(LocalApiState::default())
+        .manage(CacheSubscriptions::default())
+        .manage(LocalApiState {
+            port: local_api_port,
+            window_tokens: window_tokens_enabled(),
+            ..Default::default()
+        })
         .invoke_handler(tauri::generate_handler![
             list_supported_secret_keys,
             get_secret,
+            get_secret_status,
+            get_secrets_status,
+            get_secret_sources,
+            get_secret_audit_log,
             set_secret,
+            set_secrets,
             delete_secret,
+            validate_secret,
+            get_secret_store_status,
+            unlock_secret_vault,
+            list_secret_profiles,
+            switch_secret_profile,
+            delete_secret_profile,
+            register_custom_secret,
+            unregister_custom_secret,
+            export_secrets_bundle,
+            preview_secrets_bundle,
+            import_secrets_bundle,
             get_local_api_token,
+            get_local_api_port,
+            get_local_api_status,
             read_cache_entry,
             write_cache_entry,
+            list_cache_entries,
+            get_cache_stats,
+            delete_cache_entry,
+            delete_cache_prefix,
+            clear_cache,
+            subscribe_cache_changes,
+            unsubscribe_cache_changes,
             open_logs_folder,
             open_sidecar_log_file,
             open_settings_window_command,
@@ -575,22 +3398,269 @@ fn main() {
             fetch_polymarket
         ])
         .setup(|app| {
-            if let Err(err) = start_local_api(&app.handle()) {
+            app.manage(init_secret_store(app.handle()));
+            app.manage(init_cache_store(app.handle()));
+            spawn_cache_sweeper(app.handle());
+            reap_stale_local_api(app.handle());
+            if let Err(err) = start_local_api(app.handle()) {
                 append_desktop_log(
-                    &app.handle(),
+                    app.handle(),
                     "ERROR",
                     &format!("local API sidecar failed to start: {err}"),
                 );
                 eprintln!("[tauri] local API sidecar failed to start: {err}");
             }
+            // Started either way: a failed first launch is retried with the usual backoff.
+            spawn_local_api_supervisor(app.handle());
 
             Ok(())
         })
-        .build(tauri::generate_context!())
+        .build(context)
         .expect("error while running world-monitor tauri application")
-        .run(|app, event| {
-            if matches!(event, RunEvent::ExitRequested { .. } | RunEvent::Exit) {
-                stop_local_api(&app);
-            }
+        .run(|app, event| match event {
+            RunEvent::ExitRequested { .. } | RunEvent::Exit => stop_local_api(app),
+            RunEvent::WindowEvent {
+                label,
+                event: WindowEvent::Destroyed,
+                ..
+            } => drop_cache_subscriptions(app, &label),
+            _ => {}
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csp_port_rewrite_replaces_every_loopback_source() {
+        let bundled = format!(
+            "connect-src 'self' http://127.0.0.1:{DEFAULT_LOCAL_API_PORT}; frame-src http://127.0.0.1:{DEFAULT_LOCAL_API_PORT}"
+        );
+        let mut csp = Some(Csp::Policy(bundled));
+        rewrite_csp_port(&mut csp, 51234);
+        assert_eq!(
+            csp.map(|csp| csp.to_string()).as_deref(),
+            Some("connect-src 'self' http://127.0.0.1:51234; frame-src http://127.0.0.1:51234")
+        );
+
+        let mut none = None;
+        rewrite_csp_port(&mut none, 51234);
+        assert!(none.is_none());
+    }
+
+    #[test]
+    fn local_api_port_parsing() {
+        assert_eq!(parse_local_api_port("51234"), Ok(51234));
+        assert_eq!(parse_local_api_port(" 8080\n"), Ok(8080));
+        for invalid in ["0", "", "65536", "-1", "port"] {
+            assert!(parse_local_api_port(invalid).is_err(), "{invalid:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn allocated_port_is_free_loopback_port() {
+        let port = allocate_loopback_port().unwrap();
+        assert_ne!(port, 0);
+        TcpListener::bind((Ipv4Addr::LOCALHOST, port)).expect("allocated port should be bindable");
+    }
+
+    #[test]
+    fn every_window_may_listen_for_events() {
+        let capabilities: Vec<Value> = [
+            include_str!("../capabilities/default.json"),
+            include_str!("../capabilities/settings.json"),
+        ]
+        .iter()
+        .map(|raw| serde_json::from_str(raw).unwrap())
+        .collect();
+        let can_listen = |label: &str| {
+            capabilities.iter().any(|capability| {
+                let windows = capability["windows"].as_array().unwrap();
+                let permissions = capability["permissions"].as_array().unwrap();
+                windows.iter().any(|window| window == label)
+                    && permissions
+                        .iter()
+                        .any(|permission| permission == "core:default" || permission == "core:event:default")
+            })
+        };
+        assert!(can_listen("main"));
+        assert!(can_listen("settings"));
+    }
+
+    #[test]
+    fn cache_changes_reach_subscribers_in_other_windows() {
+        let mut entries = HashMap::new();
+        entries.insert(
+            format!("{CACHE_CHANGED_EVENT}/1"),
+            CacheSubscription {
+                window: "settings".to_string(),
+                prefixes: vec!["news:".to_string()],
+            },
+        );
+        entries.insert(
+            format!("{CACHE_CHANGED_EVENT}/2"),
+            CacheSubscription {
+                window: "main".to_string(),
+                prefixes: vec!["markets:".to_string()],
+            },
+        );
+        let change = |key: Option<&str>, prefix: Option<&str>| CacheChange {
+            action: CacheChangeAction::Write,
+            key: key.map(str::to_string),
+            prefix: prefix.map(str::to_string),
+            timestamp: 0,
+            source: None,
+        };
+
+        let write = change(Some("news:feed"), None);
+        assert_eq!(cache_change_recipients(&entries, &write), [("cache-changed/1", "settings")]);
+        let delete_all = change(None, None);
+        let mut recipients = cache_change_recipients(&entries, &delete_all);
+        recipients.sort_unstable();
+        assert_eq!(recipients, [("cache-changed/1", "settings"), ("cache-changed/2", "main")]);
+        assert!(cache_change_recipients(&entries, &change(Some("weather:now"), None)).is_empty());
+    }
+
+    #[test]
+    fn node_version_parsing_tolerates_partial_and_tagged_versions() {
+        assert_eq!(parse_node_version("v20.11.1\n"), Some((20, 11, 1)));
+        assert_eq!(parse_node_version("18"), Some((18, 0, 0)));
+        assert_eq!(parse_node_version("v22.3"), Some((22, 3, 0)));
+        assert_eq!(parse_node_version("v21.0.0-nightly20231010"), Some((21, 0, 0)));
+        for malformed in ["", "v", "lts/iron", "vx.1.2", "node", "-1.0.0"] {
+            assert_eq!(parse_node_version(malformed), None, "{malformed:?}");
+        }
+    }
+
+    #[test]
+    fn versioned_node_binaries_are_newest_first_and_skip_junk() {
+        let root = std::env::temp_dir().join(format!("wm-node-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for name in ["v18.19.0", "v20.11.1", "node-20.9.0", "v9.11.2", "lts", ".DS_Store", "vnext"] {
+            fs::create_dir_all(root.join(name)).unwrap();
+        }
+
+        let found = versioned_node_binaries(Some(root.clone()), &["bin", "node"]);
+        let expected: Vec<PathBuf> = ["v20.11.1", "node-20.9.0", "v18.19.0", "v9.11.2"]
+            .iter()
+            .map(|name| root.join(name).join("bin").join("node"))
+            .collect();
+        assert_eq!(found, expected);
+
+        assert!(versioned_node_binaries(None, &["node"]).is_empty());
+        assert!(versioned_node_binaries(Some(root.join("missing")), &["node"]).is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn window_tokens_are_signed_per_window_and_expiry() {
+        // RFC 4231 test case 2.
+        assert_eq!(
+            hmac_sha256_hex("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        let token = derive_window_token("master", "settings", 1_700_000_000);
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts[..3], ["wm1", "settings", "1700000000"]);
+        assert_eq!(parts[3], hmac_sha256_hex("master", "wm1.settings.1700000000"));
+        assert_eq!(parts[3].len(), 64);
+
+        assert_ne!(token, derive_window_token("master", "main", 1_700_000_000));
+        assert_ne!(token, derive_window_token("master", "settings", 1_700_000_001));
+        assert_ne!(token, derive_window_token("rotated", "settings", 1_700_000_000));
+    }
+
+    #[test]
+    fn window_token_expiry_is_one_ttl_after_now() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            window_token_expiry(now),
+            Ok(1_700_000_000 + LOCAL_API_WINDOW_TOKEN_TTL.as_secs())
+        );
+        let before_epoch = UNIX_EPOCH - LOCAL_API_WINDOW_TOKEN_TTL - Duration::from_secs(1);
+        assert!(window_token_expiry(before_epoch).is_err());
+    }
+
+    #[test]
+    fn env_allowlist_patterns() {
+        assert!(env_name_matches("PATH", "PATH"));
+        assert!(!env_name_matches("PATH", "PATHEXT"));
+        assert!(!env_name_matches("PATHEXT", "PATH"));
+        assert!(env_name_matches("LC_*", "LC_ALL"));
+        assert!(env_name_matches("LC_*", "LC_"));
+        assert!(!env_name_matches("LC_*", "LC"));
+        assert!(!env_name_matches("LC_*", "XLC_ALL"));
+        assert!(env_name_matches("*", "ANYTHING"));
+        assert!(!env_name_matches("", "PATH"));
+        // Only a trailing `*` is a wildcard.
+        assert!(!env_name_matches("*_PROXY", "HTTP_PROXY"));
+        assert_eq!(env_name_matches("https_proxy", "HTTPS_PROXY"), cfg!(windows));
+        assert_eq!(env_name_matches("lc_*", "LC_ALL"), cfg!(windows));
+
+        let allowed = |name: &str| LOCAL_API_ENV_ALLOWLIST.iter().any(|pattern| env_name_matches(pattern, name));
+        for name in ["PATH", "HOME", "LC_CTYPE", "HTTPS_PROXY", "NODE_EXTRA_CA_CERTS"] {
+            assert!(allowed(name), "{name} should be inherited");
+        }
+        for name in ["NODE_OPTIONS", "AWS_SECRET_ACCESS_KEY", "GROQ_API_KEY", "WS_RELAY_URL", "LOCAL_API_TOKEN"] {
+            assert!(!allowed(name), "{name} should not be inherited");
+        }
+    }
+
+    #[test]
+    fn pidfile_round_trip_stores_only_a_token_hash() {
+        let dir = std::env::temp_dir().join(format!("wm-pidfile-test-{}-round-trip", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{LOCAL_API_PID_FILE_PREFIX}51234{LOCAL_API_PID_FILE_SUFFIX}"));
+
+        write_local_api_pidfile_at(&path, &LocalApiPidfile::new(4242, 51234, "secret-token")).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("secret-token"));
+        let record = read_local_api_pidfile(&path).unwrap();
+        assert_eq!((record.pid, record.port, record.shell_pid), (4242, 51234, std::process::id()));
+        assert_eq!(record.token_hash, to_hex(&Sha256::digest(b"secret-token")));
+        assert!(record.started_at > 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stale_pidfile_check_discards_garbage_and_exited_processes() {
+        let dir = std::env::temp_dir().join(format!("wm-pidfile-test-{}-stale", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let pidfile = |name: &str, contents: &str| {
+            let path = dir.join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        };
+
+        for (name, contents) in [
+            ("empty.pid", ""),
+            ("binary.pid", "\u{0}\u{1}not json"),
+            ("truncated.pid", r#"{"pid":4242,"shellPid":1,"#),
+            ("negative.pid", r#"{"pid":-1,"shellPid":1,"port":1,"tokenHash":"","startedAt":0}"#),
+            ("missing-field.pid", r#"{"pid":4242,"shellPid":1,"port":1}"#),
+        ] {
+            let path = pidfile(name, contents);
+            assert!(live_local_api_pidfile(&path).is_none(), "{name}");
+            assert!(!path.exists(), "{name} should be removed");
+        }
+
+        let mut exited = Command::new(if cfg!(windows) { "cmd" } else { "true" });
+        if cfg!(windows) {
+            exited.args(["/C", "exit"]);
+        }
+        let mut exited = exited.spawn().unwrap();
+        let exited_pid = exited.id();
+        exited.wait().unwrap();
+        let path = dir.join("exited.pid");
+        write_local_api_pidfile_at(&path, &LocalApiPidfile::new(exited_pid, 51235, "token")).unwrap();
+        assert!(live_local_api_pidfile(&path).is_none());
+        assert!(!path.exists());
+
+        let path = dir.join("running.pid");
+        write_local_api_pidfile_at(&path, &LocalApiPidfile::new(std::process::id(), 51236, "token")).unwrap();
+        assert_eq!(live_local_api_pidfile(&path).map(|record| record.port), Some(51236));
+        assert!(path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
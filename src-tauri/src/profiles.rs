@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Profile whose secrets live under the original, unprefixed names, so installs that predate
+/// profiles keep their keys.
+pub const DEFAULT_PROFILE: &str = "default";
+const MAX_PROFILE_NAME_LEN: usize = 32;
+
+/// Persisted list of profiles and which one the shell uses; `secret-profiles.json` in the app
+/// data directory. Only names are stored here, never secrets.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileManifest {
+    pub active: String,
+    pub profiles: Vec<String>,
+}
+
+impl Default for ProfileManifest {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE.to_string(),
+            profiles: vec![DEFAULT_PROFILE.to_string()],
+        }
+    }
+}
+
+/// Lower-case letters, digits and dashes, starting with a letter or digit (e.g. `team-shared`).
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_PROFILE_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid profile name {name:?}: use up to {MAX_PROFILE_NAME_LEN} lower-case letters, digits and dashes"
+        ))
+    }
+}
+
+/// Keyring service name for a profile: `world-monitor` for the default, `world-monitor/<name>` otherwise.
+pub fn keyring_service(base: &str, profile: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        base.to_string()
+    } else {
+        format!("{base}/{profile}")
+    }
+}
+
+/// Name of a secret inside the file vault, which holds every profile under one passphrase.
+pub fn vault_key(profile: &str, key: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        key.to_string()
+    } else {
+        format!("{profile}/{key}")
+    }
+}
+
+impl ProfileManifest {
+    /// A missing or unreadable manifest falls back to the default profile.
+    pub fn load(path: &Path) -> Self {
+        let manifest = fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<ProfileManifest>(&data).ok())
+            .unwrap_or_default();
+        manifest.normalized()
+    }
+
+    fn normalized(mut self) -> Self {
+        self.profiles.retain(|name| validate_profile_name(name).is_ok());
+        if !self.profiles.iter().any(|name| name == DEFAULT_PROFILE) {
+            self.profiles.insert(0, DEFAULT_PROFILE.to_string());
+        }
+        if !self.profiles.contains(&self.active) {
+            self.active = DEFAULT_PROFILE.to_string();
+        }
+        self
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize secret profiles: {e}"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create app data directory {}: {e}", parent.display()))?;
+        }
+        fs::write(path, data).map_err(|e| format!("Failed to write secret profiles {}: {e}", path.display()))
+    }
+}
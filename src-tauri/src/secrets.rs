@@ -0,0 +1,813 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretKind {
+    ApiKey,
+    Url,
+    ClientId,
+    ClientSecret,
+}
+
+/// Everything the shell and the settings UI need to know about one supported secret.
+/// `pattern` is a format check shared by Rust and the webview, so it sticks to the
+/// syntax both regex engines agree on.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretDescriptor {
+    pub id: &'static str,
+    pub label: &'static str,
+    pub kind: SecretKind,
+    pub pattern: &'static str,
+    pub feature: &'static str,
+    pub required: bool,
+    pub sensitive: bool,
+    pub signup_url: Option<&'static str>,
+}
+
+const ANY_TOKEN: &str = r"^\S{8,}$";
+const HTTP_OR_WS_URL: &str = r"^(https?|wss?)://[^\s/?#]+\S*$";
+
+pub const SECRET_REGISTRY: &[SecretDescriptor] = &[
+    SecretDescriptor {
+        id: "GROQ_API_KEY",
+        label: "Groq API key",
+        kind: SecretKind::ApiKey,
+        pattern: r"^gsk_[A-Za-z0-9]{20,}$",
+        feature: "aiGroq",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://console.groq.com/keys"),
+    },
+    SecretDescriptor {
+        id: "OPENROUTER_API_KEY",
+        label: "OpenRouter API key",
+        kind: SecretKind::ApiKey,
+        pattern: r"^sk-or-[A-Za-z0-9-]{20,}$",
+        feature: "aiOpenRouter",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://openrouter.ai/settings/keys"),
+    },
+    SecretDescriptor {
+        id: "FRED_API_KEY",
+        label: "FRED API key",
+        kind: SecretKind::ApiKey,
+        pattern: r"^[a-z0-9]{32}$",
+        feature: "economicFred",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://fred.stlouisfed.org/docs/api/api_key.html"),
+    },
+    SecretDescriptor {
+        id: "EIA_API_KEY",
+        label: "EIA API key",
+        kind: SecretKind::ApiKey,
+        pattern: r"^[A-Za-z0-9]{20,}$",
+        feature: "energyEia",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://www.eia.gov/opendata/register.php"),
+    },
+    SecretDescriptor {
+        id: "CLOUDFLARE_API_TOKEN",
+        label: "Cloudflare API token",
+        kind: SecretKind::ApiKey,
+        pattern: r"^[A-Za-z0-9_-]{32,}$",
+        feature: "internetOutages",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://dash.cloudflare.com/profile/api-tokens"),
+    },
+    SecretDescriptor {
+        id: "ACLED_ACCESS_TOKEN",
+        label: "ACLED access token",
+        kind: SecretKind::ApiKey,
+        pattern: ANY_TOKEN,
+        feature: "acledConflicts",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://developer.acleddata.com/"),
+    },
+    SecretDescriptor {
+        id: "WINGBITS_API_KEY",
+        label: "Wingbits API key",
+        kind: SecretKind::ApiKey,
+        pattern: ANY_TOKEN,
+        feature: "wingbitsEnrichment",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://wingbits.com/register"),
+    },
+    SecretDescriptor {
+        id: "WS_RELAY_URL",
+        label: "AIS relay URL",
+        kind: SecretKind::Url,
+        pattern: HTTP_OR_WS_URL,
+        feature: "aisRelay",
+        required: true,
+        sensitive: false,
+        signup_url: None,
+    },
+    SecretDescriptor {
+        id: "VITE_WS_RELAY_URL",
+        label: "Browser relay URL",
+        kind: SecretKind::Url,
+        pattern: HTTP_OR_WS_URL,
+        feature: "aisRelay",
+        required: false,
+        sensitive: false,
+        signup_url: None,
+    },
+    SecretDescriptor {
+        id: "AISSTREAM_API_KEY",
+        label: "AISStream API key",
+        kind: SecretKind::ApiKey,
+        pattern: ANY_TOKEN,
+        feature: "aisRelay",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://aisstream.io/authenticate"),
+    },
+    SecretDescriptor {
+        id: "VITE_OPENSKY_RELAY_URL",
+        label: "OpenSky relay URL",
+        kind: SecretKind::Url,
+        pattern: HTTP_OR_WS_URL,
+        feature: "openskyRelay",
+        required: true,
+        sensitive: false,
+        signup_url: None,
+    },
+    SecretDescriptor {
+        id: "OPENSKY_CLIENT_ID",
+        label: "OpenSky client ID",
+        kind: SecretKind::ClientId,
+        pattern: r"^\S+$",
+        feature: "openskyRelay",
+        required: true,
+        sensitive: false,
+        signup_url: Some("https://opensky-network.org/login?view=registration"),
+    },
+    SecretDescriptor {
+        id: "OPENSKY_CLIENT_SECRET",
+        label: "OpenSky client secret",
+        kind: SecretKind::ClientSecret,
+        pattern: ANY_TOKEN,
+        feature: "openskyRelay",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://opensky-network.org/login?view=registration"),
+    },
+    SecretDescriptor {
+        id: "FINNHUB_API_KEY",
+        label: "Finnhub API key",
+        kind: SecretKind::ApiKey,
+        pattern: r"^[A-Za-z0-9]{16,}$",
+        feature: "marketsFinnhub",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://finnhub.io/register"),
+    },
+    SecretDescriptor {
+        id: "NASA_FIRMS_API_KEY",
+        label: "NASA FIRMS map key",
+        kind: SecretKind::ApiKey,
+        pattern: r"^[A-Fa-f0-9]{32}$",
+        feature: "wildfiresFirms",
+        required: true,
+        sensitive: true,
+        signup_url: Some("https://firms.modaps.eosdis.nasa.gov/api/map_key/"),
+    },
+];
+
+/// User-defined keys must start with this, so they can never shadow a built-in secret.
+pub const CUSTOM_SECRET_PREFIX: &str = "CUSTOM_";
+/// Feature id custom keys are listed under in the settings window.
+pub const CUSTOM_SECRET_FEATURE: &str = "customSecrets";
+const MAX_CUSTOM_SECRET_ID_LEN: usize = 64;
+
+/// Keys registered at runtime for handlers added under `api/`. Descriptors are leaked so they
+/// can be handed out as `&'static` like the built-in registry; there are only ever a handful.
+static CUSTOM_SECRETS: RwLock<Vec<&'static SecretDescriptor>> = RwLock::new(Vec::new());
+/// Every custom descriptor leaked so far, so registering the same key again (or toggling it back
+/// to an earlier label) reuses one instead of leaking another.
+static LEAKED_CUSTOM_SECRETS: Mutex<Vec<&'static SecretDescriptor>> = Mutex::new(Vec::new());
+
+/// One entry of the custom secret manifest.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomSecret {
+    pub id: String,
+    pub label: String,
+    pub kind: SecretKind,
+    pub sensitive: bool,
+}
+
+impl From<&SecretDescriptor> for CustomSecret {
+    fn from(descriptor: &SecretDescriptor) -> Self {
+        Self {
+            id: descriptor.id.to_string(),
+            label: descriptor.label.to_string(),
+            kind: descriptor.kind,
+            sensitive: descriptor.sensitive,
+        }
+    }
+}
+
+fn custom_secrets() -> Vec<&'static SecretDescriptor> {
+    CUSTOM_SECRETS.read().map(|custom| custom.clone()).unwrap_or_default()
+}
+
+pub fn find_secret(key: &str) -> Option<&'static SecretDescriptor> {
+    SECRET_REGISTRY
+        .iter()
+        .find(|descriptor| descriptor.id == key)
+        .or_else(|| custom_secrets().into_iter().find(|descriptor| descriptor.id == key))
+}
+
+/// Built-in ids followed by custom ones.
+pub fn secret_ids() -> Vec<&'static str> {
+    all_secrets().into_iter().map(|descriptor| descriptor.id).collect()
+}
+
+pub fn all_secrets() -> Vec<&'static SecretDescriptor> {
+    SECRET_REGISTRY.iter().chain(custom_secrets()).collect()
+}
+
+/// `CUSTOM_` followed by upper-case letters, digits and underscores, e.g. `CUSTOM_THREAT_FEED_TOKEN`.
+pub fn validate_custom_secret_id(id: &str) -> Result<(), String> {
+    let suffix = id.strip_prefix(CUSTOM_SECRET_PREFIX).unwrap_or_default();
+    let valid = !suffix.is_empty()
+        && id.len() <= MAX_CUSTOM_SECRET_ID_LEN
+        && suffix.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid custom secret name {id:?}: must start with {CUSTOM_SECRET_PREFIX} and use only A-Z, 0-9 and _ (max {MAX_CUSTOM_SECRET_ID_LEN} characters)"
+        ))
+    }
+}
+
+/// Adds (or replaces) a custom key and returns its descriptor.
+pub fn register_custom_secret(custom: &CustomSecret) -> Result<&'static SecretDescriptor, String> {
+    validate_custom_secret_id(&custom.id)?;
+    let descriptor = leaked_custom_secret(custom)?;
+    let mut registered = CUSTOM_SECRETS
+        .write()
+        .map_err(|_| "Failed to lock custom secret registry".to_string())?;
+    registered.retain(|existing| existing.id != custom.id);
+    registered.push(descriptor);
+    Ok(descriptor)
+}
+
+fn leaked_custom_secret(custom: &CustomSecret) -> Result<&'static SecretDescriptor, String> {
+    let label = custom.label.trim();
+    let label = if label.is_empty() { custom.id.as_str() } else { label };
+    let mut leaked = LEAKED_CUSTOM_SECRETS
+        .lock()
+        .map_err(|_| "Failed to lock custom secret registry".to_string())?;
+    if let Some(existing) = leaked.iter().find(|existing| {
+        existing.id == custom.id
+            && existing.label == label
+            && existing.kind == custom.kind
+            && existing.sensitive == custom.sensitive
+    }) {
+        return Ok(existing);
+    }
+    let descriptor: &'static SecretDescriptor = Box::leak(Box::new(SecretDescriptor {
+        id: Box::leak(custom.id.clone().into_boxed_str()),
+        label: Box::leak(label.to_string().into_boxed_str()),
+        kind: custom.kind,
+        pattern: if custom.kind == SecretKind::Url { HTTP_OR_WS_URL } else { ANY_TOKEN },
+        feature: CUSTOM_SECRET_FEATURE,
+        required: false,
+        sensitive: custom.sensitive,
+        signup_url: None,
+    }));
+    leaked.push(descriptor);
+    Ok(descriptor)
+}
+
+/// Returns whether the key was registered.
+pub fn unregister_custom_secret(id: &str) -> Result<bool, String> {
+    let mut registered = CUSTOM_SECRETS
+        .write()
+        .map_err(|_| "Failed to lock custom secret registry".to_string())?;
+    let before = registered.len();
+    registered.retain(|existing| existing.id != id);
+    Ok(registered.len() != before)
+}
+
+/// The custom keys in manifest form, in registration order.
+pub fn custom_secret_manifest() -> Vec<CustomSecret> {
+    custom_secrets().into_iter().map(CustomSecret::from).collect()
+}
+
+/// Result of checking a value against its descriptor's pattern.
+pub fn check_format(descriptor: &SecretDescriptor, value: &str) -> Result<(), String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("Value is empty".to_string());
+    }
+    let pattern = regex::Regex::new(descriptor.pattern)
+        .map_err(|e| format!("Invalid pattern for {}: {e}", descriptor.id))?;
+    if pattern.is_match(value) {
+        Ok(())
+    } else {
+        Err(format!("Does not look like a {}", descriptor.label))
+    }
+}
+
+/// Where the effective value of a secret came from.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretSource {
+    /// The shell's own process environment.
+    Env,
+    /// The `.env` file in the app config directory.
+    Dotenv,
+    Keyring,
+    Vault,
+}
+
+impl SecretSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SecretSource::Env => "env",
+            SecretSource::Dotenv => "dotenv",
+            SecretSource::Keyring => "keyring",
+            SecretSource::Vault => "vault",
+        }
+    }
+}
+
+pub struct ResolvedSecret {
+    pub value: String,
+    pub source: SecretSource,
+}
+
+/// What the webview may know about a secret: never the value itself.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretStatus {
+    pub key: String,
+    pub present: bool,
+    pub preview: Option<String>,
+    pub format_valid: bool,
+    pub source: Option<SecretSource>,
+}
+
+/// Sensitive values keep at most their first four and last two characters, and only when
+/// long enough that this reveals less than half; short ones are fully masked.
+pub fn mask_preview(descriptor: &SecretDescriptor, value: &str) -> String {
+    let value = value.trim();
+    if !descriptor.sensitive {
+        return value.to_string();
+    }
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 12 {
+        return "\u{2022}".repeat(8);
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 2..].iter().collect();
+    format!("{head}\u{2026}{tail}")
+}
+
+pub fn status(descriptor: &SecretDescriptor, resolved: Option<&ResolvedSecret>) -> SecretStatus {
+    let resolved = resolved.filter(|resolved| !resolved.value.trim().is_empty());
+    SecretStatus {
+        key: descriptor.id.to_string(),
+        present: resolved.is_some(),
+        preview: resolved.map(|resolved| mask_preview(descriptor, &resolved.value)),
+        format_valid: resolved.is_some_and(|resolved| check_format(descriptor, &resolved.value).is_ok()),
+        source: resolved.map(|resolved| resolved.source),
+    }
+}
+
+/// Parses `KEY=value` lines as written by most `.env` tooling: blank lines and `#` comments
+/// are skipped, an `export ` prefix is allowed, and one pair of matching quotes is removed.
+pub fn parse_dotenv(contents: &str) -> BTreeMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (name, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+                .unwrap_or(value);
+            Some((name.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProbeStatus {
+    /// The caller didn't ask for a probe, or the format check already failed.
+    Skipped,
+    /// No lightweight probe exists for this provider.
+    Unsupported,
+    Accepted,
+    Rejected,
+    Unreachable,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretProbe {
+    pub status: ProbeStatus,
+    pub endpoint: Option<String>,
+    pub http_status: Option<u16>,
+    pub latency_ms: Option<u64>,
+    pub message: Option<String>,
+}
+
+impl SecretProbe {
+    fn without_request(status: ProbeStatus, message: &str) -> Self {
+        Self {
+            status,
+            endpoint: None,
+            http_status: None,
+            latency_ms: None,
+            message: Some(message.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretVerdict {
+    pub key: String,
+    pub format_valid: bool,
+    pub format_message: Option<String>,
+    pub probe: SecretProbe,
+}
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(8);
+
+enum ProbeAuth {
+    Bearer,
+    Query(&'static str),
+    /// No credential is sent; the value itself is the URL to reach.
+    Reachability,
+}
+
+struct ProbeSpec {
+    default_base: &'static str,
+    path: &'static str,
+    auth: ProbeAuth,
+}
+
+fn probe_spec(key: &str) -> Option<ProbeSpec> {
+    let spec = match key {
+        "GROQ_API_KEY" => ProbeSpec {
+            default_base: "https://api.groq.com",
+            path: "/openai/v1/models",
+            auth: ProbeAuth::Bearer,
+        },
+        "OPENROUTER_API_KEY" => ProbeSpec {
+            default_base: "https://openrouter.ai",
+            path: "/api/v1/auth/key",
+            auth: ProbeAuth::Bearer,
+        },
+        "FRED_API_KEY" => ProbeSpec {
+            default_base: "https://api.stlouisfed.org",
+            path: "/fred/series?series_id=GDP&file_type=json",
+            auth: ProbeAuth::Query("api_key"),
+        },
+        "EIA_API_KEY" => ProbeSpec {
+            default_base: "https://api.eia.gov",
+            path: "/v2/",
+            auth: ProbeAuth::Query("api_key"),
+        },
+        "CLOUDFLARE_API_TOKEN" => ProbeSpec {
+            default_base: "https://api.cloudflare.com",
+            path: "/client/v4/user/tokens/verify",
+            auth: ProbeAuth::Bearer,
+        },
+        "FINNHUB_API_KEY" => ProbeSpec {
+            default_base: "https://finnhub.io",
+            path: "/api/v1/quote?symbol=AAPL",
+            auth: ProbeAuth::Query("token"),
+        },
+        "NASA_FIRMS_API_KEY" => ProbeSpec {
+            default_base: "https://firms.modaps.eosdis.nasa.gov",
+            path: "/mapserver/mapkey_status/",
+            auth: ProbeAuth::Query("MAP_KEY"),
+        },
+        "WS_RELAY_URL" | "VITE_WS_RELAY_URL" | "VITE_OPENSKY_RELAY_URL" => ProbeSpec {
+            default_base: "",
+            path: "",
+            auth: ProbeAuth::Reachability,
+        },
+        _ => return None,
+    };
+    Some(spec)
+}
+
+/// `WORLDMONITOR_PROBE_BASE_<KEY>` points a provider probe at another host, e.g. a local stand-in.
+fn probe_base(key: &str, default_base: &'static str) -> String {
+    env::var(format!("WORLDMONITOR_PROBE_BASE_{key}"))
+        .ok()
+        .map(|base| base.trim().trim_end_matches('/').to_string())
+        .filter(|base| !base.is_empty())
+        .unwrap_or_else(|| default_base.to_string())
+}
+
+/// Makes one cheap authenticated request to the provider and classifies the answer.
+/// 401/403 (and 400, which FRED and EIA use for a bad key) count as rejected; other failures are
+/// reported as unreachable so rate limits and outages aren't mistaken for a bad key.
+pub async fn probe_secret(descriptor: &SecretDescriptor, value: &str) -> SecretProbe {
+    let Some(spec) = probe_spec(descriptor.id) else {
+        return SecretProbe::without_request(
+            ProbeStatus::Unsupported,
+            "No lightweight check is available for this provider",
+        );
+    };
+    let value = value.trim();
+
+    let endpoint = match spec.auth {
+        ProbeAuth::Reachability => value
+            .replacen("wss://", "https://", 1)
+            .replacen("ws://", "http://", 1),
+        _ => format!("{}{}", probe_base(descriptor.id, spec.default_base), spec.path),
+    };
+    let client = match reqwest::Client::builder()
+        .use_native_tls()
+        .timeout(PROBE_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            return SecretProbe::without_request(ProbeStatus::Unreachable, &format!("HTTP client error: {err}"));
+        }
+    };
+    let mut request = client.get(&endpoint).header("Accept", "application/json");
+    match spec.auth {
+        ProbeAuth::Bearer => request = request.bearer_auth(value),
+        ProbeAuth::Query(param) => request = request.query(&[(param, value)]),
+        ProbeAuth::Reachability => {}
+    }
+
+    let started = Instant::now();
+    let result = request.send().await;
+    let latency_ms = Some(started.elapsed().as_millis() as u64);
+    let (status, http_status, message) = match result {
+        Err(err) => (ProbeStatus::Unreachable, None, Some(format!("Request failed: {err}"))),
+        Ok(response) => {
+            let code = response.status();
+            let status = match spec.auth {
+                ProbeAuth::Reachability => ProbeStatus::Accepted,
+                _ if code.is_success() => ProbeStatus::Accepted,
+                _ if code.as_u16() == 401 || code.as_u16() == 403 => ProbeStatus::Rejected,
+                _ if code.as_u16() == 400 => ProbeStatus::Rejected,
+                _ => ProbeStatus::Unreachable,
+            };
+            let message = match status {
+                ProbeStatus::Accepted if matches!(spec.auth, ProbeAuth::Reachability) => {
+                    Some(format!("Reachable (HTTP {})", code.as_u16()))
+                }
+                ProbeStatus::Accepted => None,
+                ProbeStatus::Rejected => Some(format!("{} rejected the credential", descriptor.label)),
+                _ => Some(format!("Provider answered HTTP {}", code.as_u16())),
+            };
+            (status, Some(code.as_u16()), message)
+        }
+    };
+
+    SecretProbe {
+        status,
+        // Built before the credential is attached, so it is safe to show in the UI.
+        endpoint: Some(endpoint),
+        http_status,
+        latency_ms,
+        message,
+    }
+}
+
+pub async fn validate(descriptor: &SecretDescriptor, value: &str, probe: bool) -> SecretVerdict {
+    let format = check_format(descriptor, value);
+    let probe = match (&format, probe) {
+        (Err(_), _) => SecretProbe::without_request(ProbeStatus::Skipped, "Format check failed"),
+        (Ok(()), false) => SecretProbe::without_request(ProbeStatus::Skipped, "Probe not requested"),
+        (Ok(()), true) => probe_secret(descriptor, value).await,
+    };
+    SecretVerdict {
+        key: descriptor.id.to_string(),
+        format_valid: format.is_ok(),
+        format_message: format.err(),
+        probe,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dotenv_handles_common_syntax() {
+        let parsed = parse_dotenv(
+            "# provider keys\n\
+             \n\
+             GROQ_API_KEY=gsk_plain\n\
+             export OPENROUTER_API_KEY=sk-or-exported\n\
+             FRED_API_KEY=\"double quoted\"\n\
+             EIA_API_KEY='single quoted'\n\
+             \x20 ACLED_ACCESS_TOKEN = padded \n\
+             WS_RELAY_URL=https://relay.example/ws?a=1&b=2\n\
+             not a pair\n",
+        );
+        assert_eq!(parsed.get("GROQ_API_KEY").map(String::as_str), Some("gsk_plain"));
+        assert_eq!(parsed.get("OPENROUTER_API_KEY").map(String::as_str), Some("sk-or-exported"));
+        assert_eq!(parsed.get("FRED_API_KEY").map(String::as_str), Some("double quoted"));
+        assert_eq!(parsed.get("EIA_API_KEY").map(String::as_str), Some("single quoted"));
+        assert_eq!(parsed.get("ACLED_ACCESS_TOKEN").map(String::as_str), Some("padded"));
+        assert_eq!(
+            parsed.get("WS_RELAY_URL").map(String::as_str),
+            Some("https://relay.example/ws?a=1&b=2")
+        );
+        assert_eq!(parsed.len(), 6);
+    }
+
+    #[test]
+    fn parse_dotenv_keeps_unmatched_quotes() {
+        let parsed = parse_dotenv("GROQ_API_KEY=\"unterminated\nFRED_API_KEY='mixed\"\n");
+        assert_eq!(parsed.get("GROQ_API_KEY").map(String::as_str), Some("\"unterminated"));
+        assert_eq!(parsed.get("FRED_API_KEY").map(String::as_str), Some("'mixed\""));
+    }
+
+    #[test]
+    fn check_format_trims_and_rejects_blank_values() {
+        let groq = find_secret("GROQ_API_KEY").unwrap();
+        assert_eq!(check_format(groq, ""), Err("Value is empty".to_string()));
+        assert_eq!(check_format(groq, " \t\n"), Err("Value is empty".to_string()));
+        assert_eq!(check_format(groq, "  gsk_abcdefghijklmnopqrstu\n"), Ok(()));
+        assert_eq!(
+            check_format(groq, "gsk_short"),
+            Err("Does not look like a Groq API key".to_string())
+        );
+
+        let acled = find_secret("ACLED_ACCESS_TOKEN").unwrap();
+        assert!(check_format(acled, "abcdefgh").is_ok());
+        assert!(check_format(acled, "abcdefg").is_err());
+        assert!(check_format(acled, "abcd efgh").is_err());
+
+        let fred = find_secret("FRED_API_KEY").unwrap();
+        assert!(check_format(fred, &"a1".repeat(16)).is_ok());
+        assert!(check_format(fred, &"A1".repeat(16)).is_err());
+        assert!(check_format(fred, &"a1".repeat(17)).is_err());
+    }
+
+    #[test]
+    fn check_format_url_schemes_and_hosts() {
+        let relay = find_secret("WS_RELAY_URL").unwrap();
+        for ok in [
+            "wss://relay.example/ws",
+            "ws://127.0.0.1:3004",
+            "https://relay.example",
+            "http://relay.example?x=1",
+        ] {
+            assert!(check_format(relay, ok).is_ok(), "{ok}");
+        }
+        for bad in [
+            "relay.example",
+            "ftp://relay.example",
+            "https://",
+            "https:///path",
+            "https://relay .example",
+        ] {
+            assert!(check_format(relay, bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn check_format_reports_a_broken_pattern() {
+        let broken = SecretDescriptor {
+            id: "BROKEN_KEY",
+            label: "Broken key",
+            kind: SecretKind::ApiKey,
+            pattern: "^[a-z",
+            feature: "custom",
+            required: false,
+            sensitive: true,
+            signup_url: None,
+        };
+        let err = check_format(&broken, "value").unwrap_err();
+        assert!(err.starts_with("Invalid pattern for BROKEN_KEY"), "{err}");
+    }
+
+    /// Answers one request with `status` and hands back the raw request head.
+    fn stand_in_provider(status: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let mut stream = reader.into_inner();
+            let response = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            stream.write_all(response.as_bytes()).unwrap();
+            head
+        });
+        (base, handle)
+    }
+
+    // Each probe test overrides a different key's base, so they can run in parallel.
+    fn probe_against(key: &str, base: &str, value: &str) -> SecretProbe {
+        env::set_var(format!("WORLDMONITOR_PROBE_BASE_{key}"), base);
+        let descriptor = find_secret(key).unwrap();
+        tauri::async_runtime::block_on(probe_secret(descriptor, value))
+    }
+
+    #[test]
+    fn probe_accepts_a_successful_answer() {
+        let (base, server) = stand_in_provider("200 OK");
+        let probe = probe_against("GROQ_API_KEY", &format!("{base}/"), " gsk_test ");
+        let head = server.join().unwrap();
+
+        assert!(probe.status == ProbeStatus::Accepted);
+        assert_eq!(probe.http_status, Some(200));
+        assert_eq!(probe.endpoint.as_deref(), Some(format!("{base}/openai/v1/models").as_str()));
+        assert!(head.starts_with("GET /openai/v1/models HTTP/1.1"), "{head}");
+        assert!(head.to_ascii_lowercase().contains("authorization: bearer gsk_test\r\n"), "{head}");
+    }
+
+    #[test]
+    fn probe_rejects_on_401_and_403() {
+        let (base, server) = stand_in_provider("401 Unauthorized");
+        let probe = probe_against("FRED_API_KEY", &base, "bad-key");
+        let head = server.join().unwrap();
+        assert!(probe.status == ProbeStatus::Rejected);
+        assert_eq!(probe.http_status, Some(401));
+        assert_eq!(probe.message.as_deref(), Some("FRED API key rejected the credential"));
+        assert!(head.contains("api_key=bad-key"), "{head}");
+        // The credential never reaches the endpoint shown in the UI.
+        assert!(!probe.endpoint.unwrap().contains("bad-key"));
+
+        let (base, server) = stand_in_provider("403 Forbidden");
+        let probe = probe_against("CLOUDFLARE_API_TOKEN", &base, "bad-token");
+        server.join().unwrap();
+        assert!(probe.status == ProbeStatus::Rejected);
+        assert_eq!(probe.http_status, Some(403));
+    }
+
+    #[test]
+    fn probe_treats_other_failures_as_unreachable() {
+        let (base, server) = stand_in_provider("429 Too Many Requests");
+        let probe = probe_against("OPENROUTER_API_KEY", &base, "sk-or-test");
+        server.join().unwrap();
+        assert!(probe.status == ProbeStatus::Unreachable);
+        assert_eq!(probe.http_status, Some(429));
+
+        let refused = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let probe = probe_against("EIA_API_KEY", &refused, "eia-test");
+        assert!(probe.status == ProbeStatus::Unreachable);
+        assert_eq!(probe.http_status, None);
+        assert!(probe.message.unwrap().starts_with("Request failed"));
+    }
+
+    #[test]
+    fn re_registering_a_custom_secret_reuses_its_descriptor() {
+        let custom = CustomSecret {
+            id: "CUSTOM_REUSE_TEST_TOKEN".to_string(),
+            label: "Reuse test".to_string(),
+            kind: SecretKind::ApiKey,
+            sensitive: true,
+        };
+        let first = register_custom_secret(&custom).unwrap();
+        assert!(unregister_custom_secret(&custom.id).unwrap());
+        let again = register_custom_secret(&custom).unwrap();
+        assert!(std::ptr::eq(first, again));
+
+        let relabelled = CustomSecret { label: "Renamed".to_string(), ..custom.clone() };
+        let renamed = register_custom_secret(&relabelled).unwrap();
+        assert!(!std::ptr::eq(first, renamed));
+        assert_eq!(find_secret(&custom.id).map(|descriptor| descriptor.label), Some("Renamed"));
+        assert!(std::ptr::eq(register_custom_secret(&custom).unwrap(), first));
+        assert_eq!(custom_secrets().iter().filter(|descriptor| descriptor.id == custom.id).count(), 1);
+        unregister_custom_secret(&custom.id).unwrap();
+    }
+}
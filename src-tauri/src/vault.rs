@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+
+const VAULT_FORMAT_VERSION: u32 = 1;
+const ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+const ARGON2_ITERATIONS: u32 = 3;
+const ARGON2_PARALLELISM: u32 = 1;
+
+/// On-disk layout, shared by the vault and export bundles. The payload is sealed with
+/// ChaCha20-Poly1305 under a key derived from the passphrase with Argon2id; only KDF
+/// parameters are in the clear.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VaultFile {
+    version: u32,
+    kdf: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Passphrase-encrypted secret store used when the OS keyring is unavailable.
+/// Starts locked; `unlock` derives the key and loads (or creates) the file.
+pub struct FileVault {
+    path: PathBuf,
+    unlocked: Option<UnlockedVault>,
+}
+
+struct UnlockedVault {
+    key: [u8; 32],
+    salt: [u8; 16],
+    secrets: BTreeMap<String, String>,
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, String> {
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.len() == 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| "Vault file is corrupt: invalid hex".to_string())
+        })
+        .collect()
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+) -> Result<[u8; 32], String> {
+    let params = Params::new(memory_kib, iterations, parallelism, Some(32))
+        .map_err(|e| format!("Invalid vault KDF parameters: {e}"))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive vault key: {e}"))?;
+    Ok(key)
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], String> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate random bytes: {e}"))?;
+    Ok(bytes)
+}
+
+fn encrypt(key: &[u8; 32], salt: &[u8; 16], plaintext: &[u8]) -> Result<String, String> {
+    let nonce = random_bytes::<12>()?;
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| "Failed to encrypt payload".to_string())?;
+    let file = VaultFile {
+        version: VAULT_FORMAT_VERSION,
+        kdf: "argon2id".to_string(),
+        memory_kib: ARGON2_MEMORY_KIB,
+        iterations: ARGON2_ITERATIONS,
+        parallelism: ARGON2_PARALLELISM,
+        salt: encode_hex(salt),
+        nonce: encode_hex(&nonce),
+        ciphertext: encode_hex(&ciphertext),
+    };
+    serde_json::to_string_pretty(&file).map_err(|e| format!("Failed to serialize encrypted payload: {e}"))
+}
+
+/// A decrypted envelope, with the derived key and salt kept so the vault can re-seal without the KDF.
+struct Opened {
+    key: [u8; 32],
+    salt: [u8; 16],
+    plaintext: Vec<u8>,
+}
+
+fn decrypt(passphrase: &str, data: &str) -> Result<Opened, String> {
+    let file: VaultFile = serde_json::from_str(data).map_err(|e| format!("Vault file is corrupt: {e}"))?;
+    if file.version != VAULT_FORMAT_VERSION || file.kdf != "argon2id" {
+        return Err(format!(
+            "Unsupported vault format (version {}, kdf {})",
+            file.version, file.kdf
+        ));
+    }
+    // The header is attacker-controlled; never let it make us spend more than we would ourselves.
+    if file.memory_kib > ARGON2_MEMORY_KIB
+        || file.iterations > ARGON2_ITERATIONS
+        || file.parallelism > ARGON2_PARALLELISM
+    {
+        return Err(format!(
+            "Vault KDF parameters exceed the supported maximum (memory {} KiB, {} iterations, parallelism {})",
+            file.memory_kib, file.iterations, file.parallelism
+        ));
+    }
+    let salt: [u8; 16] = decode_hex(&file.salt)?
+        .try_into()
+        .map_err(|_| "Vault file is corrupt: bad salt".to_string())?;
+    let nonce = decode_hex(&file.nonce)?;
+    if nonce.len() != 12 {
+        return Err("Vault file is corrupt: bad nonce".to_string());
+    }
+    let ciphertext = decode_hex(&file.ciphertext)?;
+    let key = derive_key(passphrase, &salt, file.memory_kib, file.iterations, file.parallelism)?;
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| "Wrong passphrase or tampered file".to_string())?;
+    Ok(Opened { key, salt, plaintext })
+}
+
+/// Seals `plaintext` under a fresh salt and key derived from `passphrase`.
+pub fn seal(passphrase: &str, plaintext: &[u8]) -> Result<String, String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase is empty".to_string());
+    }
+    let salt = random_bytes::<16>()?;
+    let key = derive_key(passphrase, &salt, ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM)?;
+    encrypt(&key, &salt, plaintext)
+}
+
+/// Opens an envelope produced by `seal`.
+pub fn open(passphrase: &str, data: &str) -> Result<Vec<u8>, String> {
+    decrypt(passphrase, data).map(|opened| opened.plaintext)
+}
+
+impl FileVault {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, unlocked: None }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.unlocked.is_some()
+    }
+
+    /// Opens an existing vault, or creates an empty one sealed with `passphrase` if none exists.
+    pub fn unlock(&mut self, passphrase: &str) -> Result<(), String> {
+        if passphrase.is_empty() {
+            return Err("Vault passphrase is empty".to_string());
+        }
+        if !self.exists() {
+            let salt = random_bytes::<16>()?;
+            let key = derive_key(passphrase, &salt, ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM)?;
+            self.unlocked = Some(UnlockedVault {
+                key,
+                salt,
+                secrets: BTreeMap::new(),
+            });
+            return self.persist();
+        }
+
+        let data = fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read vault {}: {e}", self.path.display()))?;
+        let Opened { key, salt, plaintext } = decrypt(passphrase, &data)?;
+        let secrets = serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Vault contents are corrupt: {e}"))?;
+        self.unlocked = Some(UnlockedVault { key, salt, secrets });
+        Ok(())
+    }
+
+    fn unlocked(&self) -> Result<&UnlockedVault, String> {
+        self.unlocked
+            .as_ref()
+            .ok_or_else(|| "Secret vault is locked".to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+        Ok(self.unlocked()?.secrets.get(key).cloned())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        self.apply(&[(key.to_string(), Some(value.to_string()))])
+    }
+
+    pub fn remove(&mut self, key: &str) -> Result<(), String> {
+        if self.unlocked()?.secrets.contains_key(key) {
+            self.apply(&[(key.to_string(), None)])?;
+        }
+        Ok(())
+    }
+
+    /// Applies several sets (`Some`) and removals (`None`) with a single re-seal. If the write
+    /// fails the in-memory map is rolled back, so the vault is never half-updated.
+    pub fn apply(&mut self, changes: &[(String, Option<String>)]) -> Result<(), String> {
+        let vault = self
+            .unlocked
+            .as_mut()
+            .ok_or_else(|| "Secret vault is locked".to_string())?;
+        let previous = vault.secrets.clone();
+        for (key, value) in changes {
+            match value {
+                Some(value) => vault.secrets.insert(key.clone(), value.clone()),
+                None => vault.secrets.remove(key),
+            };
+        }
+        let result = self.persist();
+        if result.is_err() {
+            if let Some(vault) = self.unlocked.as_mut() {
+                vault.secrets = previous;
+            }
+        }
+        result
+    }
+
+    /// Re-seals the whole map under a fresh nonce and swaps it in with a rename, so a crash
+    /// mid-write leaves the previous vault intact. The temp file is owner-only from creation and
+    /// is flushed, along with the directory entry, before the call returns.
+    fn persist(&self) -> Result<(), String> {
+        let vault = self.unlocked()?;
+        let plaintext = serde_json::to_vec(&vault.secrets)
+            .map_err(|e| format!("Failed to serialize vault: {e}"))?;
+        let data = encrypt(&vault.key, &vault.salt, &plaintext)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create vault directory {}: {e}", parent.display()))?;
+        }
+        let tmp_path = self.path.with_extension("vault.tmp");
+        // A leftover temp file would keep its old mode, since `mode` only applies on creation.
+        let _ = fs::remove_file(&tmp_path);
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&tmp_path)
+            .map_err(|e| format!("Failed to create vault {}: {e}", tmp_path.display()))?;
+        file.write_all(data.as_bytes())
+            .and_then(|()| file.sync_all())
+            .map_err(|e| format!("Failed to write vault {}: {e}", tmp_path.display()))?;
+        drop(file);
+        fs::rename(&tmp_path, &self.path)
+            .map_err(|e| format!("Failed to replace vault {}: {e}", self.path.display()))?;
+        #[cfg(unix)]
+        if let Some(parent) = self.path.parent() {
+            let dir = fs::File::open(parent)
+                .map_err(|e| format!("Failed to open vault directory {}: {e}", parent.display()))?;
+            dir.sync_all()
+                .map_err(|e| format!("Failed to sync vault directory {}: {e}", parent.display()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_vault_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wm-vault-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("secrets.vault")
+    }
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let sealed = seal("correct horse", b"payload").unwrap();
+        assert_eq!(open("correct horse", &sealed).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn open_rejects_wrong_passphrase() {
+        let sealed = seal("correct horse", b"payload").unwrap();
+        assert_eq!(open("battery staple", &sealed).unwrap_err(), "Wrong passphrase or tampered file");
+    }
+
+    #[test]
+    fn open_rejects_tampered_ciphertext() {
+        let sealed = seal("correct horse", b"payload").unwrap();
+        let mut file: VaultFile = serde_json::from_str(&sealed).unwrap();
+        let flipped = if file.ciphertext.starts_with('0') { "1" } else { "0" };
+        file.ciphertext.replace_range(..1, flipped);
+        let tampered = serde_json::to_string(&file).unwrap();
+        assert_eq!(open("correct horse", &tampered).unwrap_err(), "Wrong passphrase or tampered file");
+    }
+
+    #[test]
+    fn open_rejects_excessive_kdf_parameters() {
+        let sealed = seal("correct horse", b"payload").unwrap();
+        let mut file: VaultFile = serde_json::from_str(&sealed).unwrap();
+        file.memory_kib = ARGON2_MEMORY_KIB * 64;
+        let inflated = serde_json::to_string(&file).unwrap();
+        assert!(open("correct horse", &inflated).unwrap_err().contains("exceed the supported maximum"));
+    }
+
+    #[test]
+    fn unlock_persists_and_reopens() {
+        let path = temp_vault_path("reopen");
+        let mut vault = FileVault::new(path.clone());
+        vault.unlock("correct horse").unwrap();
+        vault.set("GROQ_API_KEY", "gsk_test").unwrap();
+
+        let mut reopened = FileVault::new(path.clone());
+        assert!(reopened.unlock("battery staple").is_err());
+        reopened.unlock("correct horse").unwrap();
+        assert_eq!(reopened.get("GROQ_API_KEY").unwrap().as_deref(), Some("gsk_test"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    /// Replaces the vault file with a non-empty directory, so the final rename of a write fails.
+    fn block_vault_writes(path: &Path) {
+        fs::remove_file(path).unwrap();
+        fs::create_dir(path).unwrap();
+        fs::write(path.join("occupied"), "").unwrap();
+    }
+
+    #[test]
+    fn set_and_remove_keep_the_map_when_the_write_fails() {
+        let path = temp_vault_path("single-rollback");
+        let mut vault = FileVault::new(path.clone());
+        vault.unlock("correct horse").unwrap();
+        vault.set("GROQ_API_KEY", "gsk_old").unwrap();
+
+        block_vault_writes(&path);
+        assert!(vault.set("GROQ_API_KEY", "gsk_new").is_err());
+        assert!(vault.set("OPENROUTER_API_KEY", "sk-or-new").is_err());
+        assert!(vault.remove("GROQ_API_KEY").is_err());
+        assert_eq!(vault.get("GROQ_API_KEY").unwrap().as_deref(), Some("gsk_old"));
+        assert_eq!(vault.get("OPENROUTER_API_KEY").unwrap(), None);
+        // Removing a key that isn't there doesn't need a write.
+        assert!(vault.remove("OPENROUTER_API_KEY").is_ok());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn apply_rolls_back_when_the_write_fails() {
+        let path = temp_vault_path("rollback");
+        let mut vault = FileVault::new(path.clone());
+        vault.unlock("correct horse").unwrap();
+        vault.set("GROQ_API_KEY", "gsk_old").unwrap();
+
+        block_vault_writes(&path);
+        let changes = vec![
+            ("GROQ_API_KEY".to_string(), Some("gsk_new".to_string())),
+            ("OPENROUTER_API_KEY".to_string(), Some("sk-or-new".to_string())),
+        ];
+        assert!(vault.apply(&changes).is_err());
+        assert_eq!(vault.get("GROQ_API_KEY").unwrap().as_deref(), Some("gsk_old"));
+        assert_eq!(vault.get("OPENROUTER_API_KEY").unwrap(), None);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
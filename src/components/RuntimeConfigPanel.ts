@@ -1,32 +1,40 @@
 import { Panel } from './Panel';
 import {
   RUNTIME_FEATURES,
+  getFeatureSecrets,
   getRuntimeConfigSnapshot,
+  getSecretDescriptor,
   getSecretState,
   isFeatureAvailable,
   isFeatureEnabled,
+  registerCustomSecret,
   setFeatureToggle,
   setSecretValue,
+  setSecretValues,
   subscribeRuntimeConfig,
+  unregisterCustomSecret,
+  validateSecret,
   type RuntimeFeatureDefinition,
   type RuntimeSecretKey,
+  type SecretVerdict,
 } from '@/services/runtime-config';
 import { invokeTauri } from '@/services/tauri-bridge';
 import { escapeHtml } from '@/utils/sanitize';
 import { isDesktopRuntime } from '@/services/runtime';
 
-const SIGNUP_URLS: Partial<Record<RuntimeSecretKey, string>> = {
-  GROQ_API_KEY: 'https://console.groq.com/keys',
-  OPENROUTER_API_KEY: 'https://openrouter.ai/settings/keys',
-  FRED_API_KEY: 'https://fred.stlouisfed.org/docs/api/api_key.html',
-  EIA_API_KEY: 'https://www.eia.gov/opendata/register.php',
-  CLOUDFLARE_API_TOKEN: 'https://dash.cloudflare.com/profile/api-tokens',
-  ACLED_ACCESS_TOKEN: 'https://developer.acleddata.com/',
-  WINGBITS_API_KEY: 'https://wingbits.com/register',
-  AISSTREAM_API_KEY: 'https://aisstream.io/authenticate',
-  OPENSKY_CLIENT_ID: 'https://opensky-network.org/login?view=registration',
-  OPENSKY_CLIENT_SECRET: 'https://opensky-network.org/login?view=registration',
-};
+function describeVerdict(verdict: SecretVerdict): { text: string; ok: boolean } {
+  if (!verdict.formatValid) return { text: verdict.formatMessage ?? 'Invalid format', ok: false };
+  switch (verdict.probe.status) {
+    case 'accepted':
+      return { text: verdict.probe.message ?? 'Accepted by provider', ok: true };
+    case 'rejected':
+      return { text: verdict.probe.message ?? 'Rejected by provider', ok: false };
+    case 'unreachable':
+      return { text: verdict.probe.message ?? 'Provider unreachable', ok: false };
+    default:
+      return { text: 'Format OK', ok: true };
+  }
+}
 
 interface RuntimeConfigPanelOptions {
   mode?: 'full' | 'alert';
@@ -48,9 +56,7 @@ export class RuntimeConfigPanel extends Panel {
   }
 
   public async commitPendingSecrets(): Promise<void> {
-    for (const [key, value] of this.pendingSecrets) {
-      await setSecretValue(key, value);
-    }
+    await setSecretValues(this.pendingSecrets);
     this.pendingSecrets.clear();
   }
 
@@ -107,9 +113,17 @@ export class RuntimeConfigPanel extends Panel {
   private renderFeature(feature: RuntimeFeatureDefinition): string {
     const enabled = isFeatureEnabled(feature.id);
     const available = isFeatureAvailable(feature.id);
-    const secrets = feature.requiredSecrets.map((key) => this.renderSecretRow(key)).join('');
+    const secrets = getFeatureSecrets(feature).map((key) => this.renderSecretRow(key)).join('');
     const desktop = isDesktopRuntime();
     const fallbackHtml = available ? '' : `<p class="runtime-feature-fallback fallback">${escapeHtml(feature.fallback)}</p>`;
+    const customFormHtml = feature.id === 'customSecrets' && desktop && this.mode === 'full'
+      ? `<form class="runtime-custom-secret-form" data-custom-secret-form>
+          <input name="id" placeholder="CUSTOM_NAME" autocomplete="off" spellcheck="false" required>
+          <input name="label" placeholder="Label" autocomplete="off">
+          <select name="kind"><option value="api-key">Key</option><option value="url">URL</option></select>
+          <button type="submit" class="runtime-secret-check">Add</button>
+        </form>`
+      : '';
 
     return `
       <section class="runtime-feature ${available ? 'available' : 'degraded'}">
@@ -121,6 +135,7 @@ export class RuntimeConfigPanel extends Panel {
           <span class="runtime-pill ${available ? 'ok' : 'warn'}">${available ? 'Ready' : 'Needs Keys'}</span>
         </header>
         <div class="runtime-secrets">${secrets}</div>
+        ${customFormHtml}
         ${fallbackHtml}
       </section>
     `;
@@ -128,16 +143,27 @@ export class RuntimeConfigPanel extends Panel {
 
   private renderSecretRow(key: RuntimeSecretKey): string {
     const state = getSecretState(key);
-    const status = !state.present ? 'Missing' : state.valid ? `Valid (${state.source})` : 'Looks invalid';
-    const signupUrl = SIGNUP_URLS[key];
+    const descriptor = getSecretDescriptor(key);
+    const optional = descriptor?.required === false;
+    const status = !state.present ? (optional ? 'Optional' : 'Missing') : state.valid ? `Valid (${state.source})` : 'Looks invalid';
+    const signupUrl = descriptor?.signupUrl;
     const linkHtml = signupUrl
-      ? ` <a href="#" data-signup-url="${signupUrl}" class="runtime-secret-link" title="Get API key">&#x2197;</a>`
+      ? ` <a href="#" data-signup-url="${escapeHtml(signupUrl)}" class="runtime-secret-link" title="Get API key">&#x2197;</a>`
       : '';
+    const labelHtml = descriptor ? `<span class="runtime-secret-label">${escapeHtml(descriptor.label)}</span> ` : '';
+    const inputType = descriptor?.sensitive === false ? 'text' : 'password';
+    const placeholder = state.preview ?? (descriptor?.kind === 'url' ? 'Set URL' : 'Set secret');
+    const checkable = isDesktopRuntime() && this.mode === 'full';
+    const custom = descriptor?.feature === 'customSecrets';
+    const actionHtml = custom
+      ? `<button type="button" class="runtime-secret-check" data-remove-secret="${escapeHtml(key)}">Remove</button>`
+      : `<button type="button" class="runtime-secret-check" data-check-secret="${escapeHtml(key)}">Check</button>`;
     return `
-      <div class="runtime-secret-row">
-        <div class="runtime-secret-key"><code>${escapeHtml(key)}</code>${linkHtml}</div>
-        <span class="runtime-secret-status ${state.valid ? 'ok' : 'warn'}">${escapeHtml(status)}</span>
-        <input type="password" data-secret="${key}" placeholder="Set secret" autocomplete="off" ${isDesktopRuntime() ? '' : 'disabled'}>
+      <div class="runtime-secret-row${checkable ? ' checkable' : ''}">
+        <div class="runtime-secret-key">${labelHtml}<code>${escapeHtml(key)}</code>${linkHtml}</div>
+        <span class="runtime-secret-status ${state.valid || (optional && !state.present) ? 'ok' : 'warn'}">${escapeHtml(status)}</span>
+        <input type="${inputType}" data-secret="${escapeHtml(key)}" placeholder="${escapeHtml(placeholder)}" autocomplete="off" ${isDesktopRuntime() ? '' : 'disabled'}>
+        ${checkable ? actionHtml : ''}
       </div>
     `;
   }
@@ -175,6 +201,60 @@ export class RuntimeConfigPanel extends Panel {
       });
     });
 
+    this.content.querySelectorAll<HTMLButtonElement>('button[data-check-secret]').forEach((button) => {
+      button.addEventListener('click', () => {
+        const key = button.dataset.checkSecret as RuntimeSecretKey | undefined;
+        if (!key) return;
+        const row = button.closest('.runtime-secret-row');
+        const status = row?.querySelector<HTMLElement>('.runtime-secret-status');
+        const typed = row?.querySelector<HTMLInputElement>('input[data-secret]')?.value;
+        button.disabled = true;
+        if (status) status.textContent = 'Checking…';
+        void validateSecret(key, typed || this.pendingSecrets.get(key))
+          .then((verdict) => {
+            const { text, ok } = describeVerdict(verdict);
+            if (!status) return;
+            status.textContent = text;
+            status.classList.toggle('ok', ok);
+            status.classList.toggle('warn', !ok);
+          })
+          .catch((error) => {
+            if (status) status.textContent = String(error);
+          })
+          .finally(() => {
+            button.disabled = false;
+          });
+      });
+    });
+
+    this.content.querySelectorAll<HTMLButtonElement>('button[data-remove-secret]').forEach((button) => {
+      button.addEventListener('click', () => {
+        const key = button.dataset.removeSecret as RuntimeSecretKey | undefined;
+        if (!key) return;
+        button.disabled = true;
+        this.pendingSecrets.delete(key);
+        void unregisterCustomSecret(key).catch((error) => {
+          button.disabled = false;
+          console.warn('[runtime-config] Failed to remove custom secret', error);
+        });
+      });
+    });
+
+    this.content.querySelector<HTMLFormElement>('form[data-custom-secret-form]')?.addEventListener('submit', (event) => {
+      event.preventDefault();
+      const form = event.currentTarget as HTMLFormElement;
+      const data = new FormData(form);
+      const id = String(data.get('id') ?? '').trim().toUpperCase();
+      const label = String(data.get('label') ?? '').trim();
+      const kind = data.get('kind') === 'url' ? 'url' : 'api-key';
+      void registerCustomSecret(id, label, kind).catch((error) => {
+        const input = form.querySelector<HTMLInputElement>('input[name="id"]');
+        input?.setCustomValidity(String(error));
+        input?.reportValidity();
+        input?.setCustomValidity('');
+      });
+    });
+
     this.content.querySelectorAll<HTMLInputElement>('input[data-secret]').forEach((input) => {
       input.addEventListener('change', () => {
         const key = input.dataset.secret as RuntimeSecretKey | undefined;
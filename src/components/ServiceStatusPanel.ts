@@ -1,6 +1,12 @@
 import { Panel } from './Panel';
 import { escapeHtml } from '@/utils/sanitize';
 import { isDesktopRuntime } from '@/services/runtime';
+import {
+  describeLocalApiStatus,
+  getLocalApiStatus,
+  subscribeLocalApiStatus,
+  type LocalApiStatus,
+} from '@/services/local-api-status';
 import {
   getDesktopReadinessChecks,
   getKeyBackedAvailabilitySummary,
@@ -53,18 +59,41 @@ export class ServiceStatusPanel extends Panel {
   private filter: CategoryFilter = 'all';
   private refreshInterval: ReturnType<typeof setInterval> | null = null;
   private localBackend: LocalBackendStatus | null = null;
+  private sidecarStatus: LocalApiStatus | null = null;
+  private unlistenSidecar: (() => void) | null = null;
+  private destroyed = false;
 
   constructor() {
     super({ id: 'service-status', title: 'Service Status', showCount: false });
     void this.fetchStatus();
     this.refreshInterval = setInterval(() => this.fetchStatus(), 60000);
+    if (isDesktopRuntime()) void this.watchSidecarStatus();
   }
 
   public destroy(): void {
+    this.destroyed = true;
     if (this.refreshInterval) {
       clearInterval(this.refreshInterval);
       this.refreshInterval = null;
     }
+    this.unlistenSidecar?.();
+    this.unlistenSidecar = null;
+  }
+
+  private async watchSidecarStatus(): Promise<void> {
+    const unlisten = await subscribeLocalApiStatus((status) => {
+      const recovered = status.phase === 'ready' && this.sidecarStatus?.phase !== 'ready';
+      this.sidecarStatus = status;
+      if (recovered) void this.fetchStatus();
+      else if (!this.loading) this.render();
+    });
+    if (this.destroyed) {
+      unlisten?.();
+      return;
+    }
+    this.unlistenSidecar = unlisten;
+    this.sidecarStatus = await getLocalApiStatus() ?? this.sidecarStatus;
+    if (!this.loading) this.render();
   }
 
   private async fetchStatus(): Promise<void> {
@@ -150,20 +179,24 @@ export class ServiceStatusPanel extends Panel {
   private renderBackendStatus(): string {
     if (!isDesktopRuntime()) return '';
 
-    if (!this.localBackend?.enabled) {
+    if (!this.localBackend?.enabled || (this.sidecarStatus && this.sidecarStatus.phase !== 'ready')) {
+      const reason = this.sidecarStatus && this.sidecarStatus.phase !== 'ready'
+        ? ` ${escapeHtml(describeLocalApiStatus(this.sidecarStatus))}.`
+        : '';
       return `
         <div class="service-status-backend warning">
-          Desktop local backend unavailable. Falling back to cloud API.
+          Desktop local backend unavailable.${reason} Falling back to cloud API.
         </div>
       `;
     }
 
-    const port = this.localBackend.port ?? 46123;
+    const port = this.sidecarStatus?.port ?? this.localBackend.port;
+    const address = port ? `127.0.0.1:${port}` : '127.0.0.1';
     const remote = this.localBackend.remoteBase ?? 'https://worldmonitor.app';
 
     return `
       <div class="service-status-backend">
-        Local backend active on <strong>127.0.0.1:${port}</strong> · cloud fallback: <strong>${escapeHtml(remote)}</strong>
+        Local backend active on <strong>${address}</strong> · cloud fallback: <strong>${escapeHtml(remote)}</strong>
       </div>
     `;
   }
@@ -0,0 +1,69 @@
+import { listenTauri, tryInvokeTauri, type TauriUnlisten } from './tauri-bridge';
+
+export type LocalApiPhase =
+  | 'not-started'
+  | 'starting'
+  | 'ready'
+  | 'exited'
+  | 'restart-scheduled'
+  | 'node-missing'
+  | 'failed'
+  | 'stopped';
+
+export interface NodeCandidate {
+  path: string;
+  source: string;
+  version: string | null;
+  accepted: boolean;
+  reason: string | null;
+}
+
+export interface LocalApiStatus {
+  phase: LocalApiPhase;
+  pid: number | null;
+  port: number;
+  uptimeMs: number | null;
+  restartCount: number;
+  exitCode: number | null;
+  restartDelayMs: number | null;
+  lastError: string | null;
+  nodeVersion: string | null;
+  nodeCandidates: NodeCandidate[];
+}
+
+const LOCAL_API_STATUS_EVENT = 'local-api-status';
+
+export async function getLocalApiStatus(): Promise<LocalApiStatus | null> {
+  return tryInvokeTauri<LocalApiStatus>('get_local_api_status');
+}
+
+export function subscribeLocalApiStatus(listener: (status: LocalApiStatus) => void): Promise<TauriUnlisten | null> {
+  return listenTauri<LocalApiStatus>(LOCAL_API_STATUS_EVENT, listener);
+}
+
+export function describeLocalApiStatus(status: LocalApiStatus): string {
+  switch (status.phase) {
+    case 'ready':
+      return `Local API ready (pid ${status.pid ?? '?'})`;
+    case 'starting':
+      return 'Local API starting…';
+    case 'restart-scheduled': {
+      const seconds = Math.round((status.restartDelayMs ?? 0) / 1000);
+      return `Local API restarting in ${seconds}s (restart #${status.restartCount})`;
+    }
+    case 'exited':
+      return `Local API exited${status.exitCode === null ? '' : ` with code ${status.exitCode}`}`;
+    case 'node-missing': {
+      const rejected = status.nodeCandidates.find((candidate) => candidate.version !== null);
+      return rejected
+        ? `Node.js ${rejected.version} at ${rejected.path} is too old — install Node 18+ or set LOCAL_API_NODE_BIN`
+        : 'Node.js not found — install Node 18+ or set LOCAL_API_NODE_BIN';
+    }
+    case 'failed':
+      return `Local API failed: ${status.lastError ?? 'unknown error'}`;
+    case 'stopped':
+      return 'Local API stopped';
+    default:
+      return 'Local API not started';
+  }
+}
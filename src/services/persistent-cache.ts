@@ -1,5 +1,5 @@
 import { isDesktopRuntime } from './runtime';
-import { invokeTauri } from './tauri-bridge';
+import { invokeTauri, listenTauri, tryInvokeTauri, type TauriUnlisten } from './tauri-bridge';
 
 type CacheEnvelope<T> = {
   key: string;
@@ -8,6 +8,7 @@ type CacheEnvelope<T> = {
 };
 
 const CACHE_PREFIX = 'worldmonitor-persistent-cache:';
+const CACHE_CHANGED_EVENT = 'cache-changed';
 
 export async function getPersistentCache<T>(key: string): Promise<CacheEnvelope<T> | null> {
   if (isDesktopRuntime()) {
@@ -27,12 +28,13 @@ export async function getPersistentCache<T>(key: string): Promise<CacheEnvelope<
   }
 }
 
-export async function setPersistentCache<T>(key: string, data: T): Promise<void> {
+/** `ttlMs` overrides the desktop cache's default expiry for this entry. */
+export async function setPersistentCache<T>(key: string, data: T, ttlMs?: number): Promise<void> {
   const payload: CacheEnvelope<T> = { key, data, updatedAt: Date.now() };
 
   if (isDesktopRuntime()) {
     try {
-      await invokeTauri<void>('write_cache_entry', { key, value: JSON.stringify(payload) });
+      await invokeTauri<void>('write_cache_entry', { key, value: JSON.stringify(payload), ttlMs });
       return;
     } catch (error) {
       console.warn('[persistent-cache] Desktop write failed; falling back to localStorage', error);
@@ -46,6 +48,103 @@ export async function setPersistentCache<T>(key: string, data: T): Promise<void>
   }
 }
 
+export interface PersistentCacheEntryInfo {
+  key: string;
+  size: number;
+  updatedAt: number;
+  accessedAt: number;
+  expiresAt: number | null;
+}
+
+export interface PersistentCacheStats {
+  path: string;
+  entries: number;
+  totalBytes: number;
+  maxBytes: number;
+  defaultTtlMs: number | null;
+  oldestUpdatedAt: number | null;
+  newestUpdatedAt: number | null;
+  recovery: { reason: string; quarantined: string; restoredFromBackup: boolean } | null;
+}
+
+/** Desktop only: stored keys with their sizes and timestamps, optionally under `prefix`. */
+export async function listPersistentCache(prefix?: string): Promise<PersistentCacheEntryInfo[]> {
+  return invokeTauri<PersistentCacheEntryInfo[]>('list_cache_entries', { prefix });
+}
+
+export async function getPersistentCacheStats(): Promise<PersistentCacheStats> {
+  return invokeTauri<PersistentCacheStats>('get_cache_stats');
+}
+
+function removeLocalCopies(matches: (key: string) => boolean): void {
+  try {
+    for (let i = localStorage.length - 1; i >= 0; i -= 1) {
+      const storageKey = localStorage.key(i);
+      if (storageKey?.startsWith(CACHE_PREFIX) && matches(storageKey.slice(CACHE_PREFIX.length))) {
+        localStorage.removeItem(storageKey);
+      }
+    }
+  } catch {
+    // Storage unavailable
+  }
+}
+
+/** Removes one entry, including any localStorage fallback copy. Returns whether the desktop cache had it. */
+export async function deletePersistentCache(key: string): Promise<boolean> {
+  removeLocalCopies((cacheKey) => cacheKey === key);
+  return isDesktopRuntime() ? invokeTauri<boolean>('delete_cache_entry', { key }) : false;
+}
+
+/** Removes every entry whose key starts with `prefix` and returns the desktop count. */
+export async function deletePersistentCachePrefix(prefix: string): Promise<number> {
+  removeLocalCopies((cacheKey) => cacheKey.startsWith(prefix));
+  return isDesktopRuntime() ? invokeTauri<number>('delete_cache_prefix', { prefix }) : 0;
+}
+
+/** Empties the cache and returns the desktop count. */
+export async function clearPersistentCache(): Promise<number> {
+  removeLocalCopies(() => true);
+  return isDesktopRuntime() ? invokeTauri<number>('clear_cache') : 0;
+}
+
+export interface PersistentCacheChange {
+  action: 'write' | 'delete' | 'evict';
+  /** Entry written or removed; null when a whole prefix was deleted. */
+  key: string | null;
+  /** Set for prefix deletes; '' means the cache was cleared. */
+  prefix: string | null;
+  timestamp: number;
+  /** Label of the window that made the change; null for the shell's own sweeps. */
+  source: string | null;
+}
+
+/**
+ * Desktop only: calls `listener` whenever any window writes or deletes a cache entry, or the
+ * shell evicts one. With `prefixes`, the shell only delivers changes under those prefixes.
+ */
+export async function subscribePersistentCacheChanges(
+  listener: (change: PersistentCacheChange) => void,
+  prefixes?: string[],
+): Promise<TauriUnlisten | null> {
+  if (!prefixes?.length) {
+    return listenTauri<PersistentCacheChange>(CACHE_CHANGED_EVENT, listener);
+  }
+
+  const ticket = await tryInvokeTauri<{ event: string; window: string }>('subscribe_cache_changes', { prefixes });
+  if (!ticket) return null;
+  const { event, window: label } = ticket;
+  const unsubscribe = () => void tryInvokeTauri<boolean>('unsubscribe_cache_changes', { subscription: event });
+  const unlisten = await listenTauri<PersistentCacheChange>(event, listener, { kind: 'WebviewWindow', label });
+  if (!unlisten) {
+    unsubscribe();
+    return null;
+  }
+  return () => {
+    unlisten();
+    unsubscribe();
+  };
+}
+
 export function cacheAgeMs(updatedAt: number): number {
   return Math.max(0, Date.now() - updatedAt);
 }
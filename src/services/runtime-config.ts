@@ -13,7 +13,11 @@ export type RuntimeSecretKey =
   | 'VITE_OPENSKY_RELAY_URL'
   | 'OPENSKY_CLIENT_ID'
   | 'OPENSKY_CLIENT_SECRET'
-  | 'AISSTREAM_API_KEY';
+  | 'AISSTREAM_API_KEY'
+  | 'VITE_WS_RELAY_URL'
+  | 'FINNHUB_API_KEY'
+  | 'NASA_FIRMS_API_KEY'
+  | `CUSTOM_${string}`;
 
 export type RuntimeFeatureId =
   | 'aiGroq'
@@ -24,7 +28,10 @@ export type RuntimeFeatureId =
   | 'acledConflicts'
   | 'wingbitsEnrichment'
   | 'aisRelay'
-  | 'openskyRelay';
+  | 'openskyRelay'
+  | 'marketsFinnhub'
+  | 'wildfiresFirms'
+  | 'customSecrets';
 
 export interface RuntimeFeatureDefinition {
   id: RuntimeFeatureId;
@@ -34,9 +41,59 @@ export interface RuntimeFeatureDefinition {
   fallback: string;
 }
 
+export type SecretKind = 'api-key' | 'url' | 'client-id' | 'client-secret';
+
+// Mirrors the Rust secret registry returned by `list_supported_secret_keys`.
+export interface SecretDescriptor {
+  id: RuntimeSecretKey;
+  label: string;
+  kind: SecretKind;
+  pattern: string;
+  feature: RuntimeFeatureId;
+  required: boolean;
+  sensitive: boolean;
+  signupUrl: string | null;
+}
+
+export type SecretProbeStatus = 'skipped' | 'unsupported' | 'accepted' | 'rejected' | 'unreachable';
+
+export interface SecretVerdict {
+  key: RuntimeSecretKey;
+  formatValid: boolean;
+  formatMessage: string | null;
+  probe: {
+    status: SecretProbeStatus;
+    endpoint: string | null;
+    httpStatus: number | null;
+    latencyMs: number | null;
+    message: string | null;
+  };
+}
+
+// Desktop builds never hold secret values in the webview: the shell reports presence,
+// a masked preview and the format check, and hands the values to the sidecar itself.
+// Desktop sources in precedence order: shell environment, `.env` file, then the keyring or vault.
+export type SecretSource = 'env' | 'dotenv' | 'keyring' | 'vault';
+
 export interface RuntimeSecretState {
-  value: string;
-  source: 'env' | 'vault';
+  source: SecretSource;
+  preview: string | null;
+  valid: boolean;
+}
+
+interface SecretStatus {
+  key: RuntimeSecretKey;
+  present: boolean;
+  preview: string | null;
+  formatValid: boolean;
+  source: SecretSource | null;
+}
+
+interface SecretBatchResult {
+  key: RuntimeSecretKey;
+  ok: boolean;
+  status: SecretStatus | null;
+  error: string | null;
 }
 
 export interface RuntimeConfig {
@@ -56,6 +113,9 @@ const defaultToggles: Record<RuntimeFeatureId, boolean> = {
   wingbitsEnrichment: true,
   aisRelay: true,
   openskyRelay: true,
+  marketsFinnhub: true,
+  wildfiresFirms: true,
+  customSecrets: true,
 };
 
 export const RUNTIME_FEATURES: RuntimeFeatureDefinition[] = [
@@ -122,6 +182,27 @@ export const RUNTIME_FEATURES: RuntimeFeatureDefinition[] = [
     requiredSecrets: ['VITE_OPENSKY_RELAY_URL', 'OPENSKY_CLIENT_ID', 'OPENSKY_CLIENT_SECRET'],
     fallback: 'Military flights fall back to limited/no data.',
   },
+  {
+    id: 'marketsFinnhub',
+    name: 'Finnhub market quotes',
+    description: 'Stock and index quotes from Finnhub.',
+    requiredSecrets: ['FINNHUB_API_KEY'],
+    fallback: 'Market quotes fall back to Yahoo Finance where available.',
+  },
+  {
+    id: 'wildfiresFirms',
+    name: 'NASA FIRMS fire detections',
+    description: 'Satellite fire detections from NASA FIRMS.',
+    requiredSecrets: ['NASA_FIRMS_API_KEY'],
+    fallback: 'Fire detection layer is hidden.',
+  },
+  {
+    id: 'customSecrets',
+    name: 'Custom API handlers',
+    description: 'Keys you registered (CUSTOM_*) for your own handlers under api/.',
+    requiredSecrets: [],
+    fallback: 'Custom handlers run without their keys.',
+  },
 ];
 
 function readEnvSecret(key: RuntimeSecretKey): string {
@@ -140,8 +221,30 @@ function readStoredToggles(): Record<RuntimeFeatureId, boolean> {
   }
 }
 
-function validateSecretValue(value: string): boolean {
-  return value.trim().length >= 8;
+let secretDescriptors: SecretDescriptor[] = [];
+
+export function getSecretDescriptor(key: RuntimeSecretKey): SecretDescriptor | undefined {
+  return secretDescriptors.find((descriptor) => descriptor.id === key);
+}
+
+// Desktop builds take a feature's secrets from the Rust registry; the static list covers web mode.
+export function getFeatureSecrets(feature: RuntimeFeatureDefinition): RuntimeSecretKey[] {
+  const registered = secretDescriptors.filter((descriptor) => descriptor.feature === feature.id);
+  return registered.length > 0 ? registered.map((descriptor) => descriptor.id) : feature.requiredSecrets;
+}
+
+function isSecretRequired(key: RuntimeSecretKey): boolean {
+  return getSecretDescriptor(key)?.required ?? true;
+}
+
+function validateSecretValue(key: RuntimeSecretKey, value: string): boolean {
+  const descriptor = getSecretDescriptor(key);
+  if (!descriptor) return value.trim().length >= 8;
+  try {
+    return new RegExp(descriptor.pattern).test(value.trim());
+  } catch {
+    return value.trim().length > 0;
+  }
 }
 
 const listeners = new Set<() => void>();
@@ -162,7 +265,7 @@ function seedSecretsFromEnvironment(): void {
   for (const key of keys) {
     const value = readEnvSecret(key);
     if (value) {
-      runtimeConfig.secrets[key] = { value, source: 'env' };
+      runtimeConfig.secrets[key] = { source: 'env', preview: null, valid: validateSecretValue(key, value) };
     }
   }
 }
@@ -185,10 +288,18 @@ export function isFeatureEnabled(featureId: RuntimeFeatureId): boolean {
   return runtimeConfig.featureToggles[featureId] !== false;
 }
 
-export function getSecretState(key: RuntimeSecretKey): { present: boolean; valid: boolean; source: 'env' | 'vault' | 'missing' } {
+export function getSecretState(key: RuntimeSecretKey): { present: boolean; valid: boolean; preview: string | null; source: SecretSource | 'missing' } {
   const state = runtimeConfig.secrets[key];
-  if (!state) return { present: false, valid: false, source: 'missing' };
-  return { present: true, valid: validateSecretValue(state.value), source: state.source };
+  if (!state) return { present: false, valid: false, preview: null, source: 'missing' };
+  return { present: true, valid: state.valid, preview: state.preview, source: state.source };
+}
+
+function applySecretStatus(status: SecretStatus): void {
+  if (status.present) {
+    runtimeConfig.secrets[status.key] = { source: status.source ?? 'vault', preview: status.preview, valid: status.formatValid };
+  } else {
+    delete runtimeConfig.secrets[status.key];
+  }
 }
 
 export function isFeatureAvailable(featureId: RuntimeFeatureId): boolean {
@@ -202,7 +313,9 @@ export function isFeatureAvailable(featureId: RuntimeFeatureId): boolean {
 
   const feature = RUNTIME_FEATURES.find(item => item.id === featureId);
   if (!feature) return false;
-  return feature.requiredSecrets.every(secretKey => getSecretState(secretKey).valid);
+  return getFeatureSecrets(feature)
+    .filter(isSecretRequired)
+    .every(secretKey => getSecretState(secretKey).valid);
 }
 
 export function setFeatureToggle(featureId: RuntimeFeatureId, enabled: boolean): void {
@@ -220,39 +333,194 @@ export async function setSecretValue(key: RuntimeSecretKey, value: string): Prom
   const sanitized = value.trim();
   if (sanitized) {
     await invokeTauri<void>('set_secret', { key, value: sanitized });
-    runtimeConfig.secrets[key] = { value: sanitized, source: 'vault' };
+    applySecretStatus(await invokeTauri<SecretStatus>('get_secret_status', { key }));
   } else {
     await invokeTauri<void>('delete_secret', { key });
     delete runtimeConfig.secrets[key];
   }
 
-  // Push to sidecar so handlers pick it up immediately
-  pushSecretToSidecar(key, sanitized || '');
+  notifyConfigChanged();
+}
 
+// Saves several secrets in one call. The shell applies all of them or none, except that a
+// keyring key whose rollback failed keeps its new value; its result reports that error.
+export async function setSecretValues(values: Map<RuntimeSecretKey, string>): Promise<void> {
+  if (!isDesktopRuntime()) {
+    console.warn('[runtime-config] Ignoring secret write outside desktop runtime');
+    return;
+  }
+  if (values.size === 0) return;
+
+  const changes = [...values].map(([key, value]) => ({ key, value: value.trim() || null }));
+  const results = await invokeTauri<SecretBatchResult[]>('set_secrets', { changes });
+  const failures = results.filter((result) => !result.ok);
+  if (failures.length > 0) {
+    throw new Error(failures.map((result) => `${result.key}: ${result.error ?? 'failed'}`).join('; '));
+  }
+  for (const result of results) {
+    if (result.status) applySecretStatus(result.status);
+  }
   notifyConfigChanged();
 }
 
-function pushSecretToSidecar(key: string, value: string): void {
-  fetch('http://127.0.0.1:46123/api/local-env-update', {
-    method: 'POST',
-    headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify({ key, value: value || null }),
-  }).catch(() => { /* sidecar not running */ });
+export interface SecretStoreStatus {
+  backend: 'keyring' | 'file';
+  locked: boolean;
+  vaultPath: string | null;
+  vaultExists: boolean;
+  reason: string;
+  writeOnly: boolean;
+}
+
+export async function getSecretStoreStatus(): Promise<SecretStoreStatus | null> {
+  if (!isDesktopRuntime()) return null;
+  return invokeTauri<SecretStoreStatus>('get_secret_store_status');
+}
+
+export async function unlockSecretVault(passphrase: string): Promise<void> {
+  await invokeTauri<void>('unlock_secret_vault', { passphrase });
+}
+
+export interface SecretSourceReport {
+  precedence: Array<'env' | 'dotenv' | 'store'>;
+  dotenvPath: string;
+  dotenvExists: boolean;
+  keys: Array<{ key: RuntimeSecretKey; effective: SecretSource | null; available: SecretSource[]; error: string | null }>;
+}
+
+export async function getSecretSources(): Promise<SecretSourceReport | null> {
+  if (!isDesktopRuntime()) return null;
+  return invokeTauri<SecretSourceReport>('get_secret_sources');
+}
+
+export interface SecretAuditEvent {
+  timestamp: number;
+  action: string;
+  key: string | null;
+  window: string;
+  profile: string;
+  success: boolean;
+  error: string | null;
+}
+
+export interface SecretAuditQuery {
+  key?: string;
+  action?: string;
+  window?: string;
+  since?: number;
+  limit?: number;
+}
+
+// Newest first; the shell records plaintext reads (including validating a stored key), writes
+// and deletes with the calling window. Status queries, which only return masked previews, aren't recorded.
+export async function getSecretAuditLog(query: SecretAuditQuery = {}): Promise<SecretAuditEvent[]> {
+  if (!isDesktopRuntime()) return [];
+  return invokeTauri<SecretAuditEvent[]>('get_secret_audit_log', { query });
+}
+
+// Registers a `CUSTOM_*` key; the shell stores and injects it like a built-in one.
+export async function registerCustomSecret(id: string, label: string, kind: 'api-key' | 'url' = 'api-key'): Promise<void> {
+  await invokeTauri<SecretDescriptor>('register_custom_secret', { id, label, kind, sensitive: kind !== 'url' });
+  await loadDesktopSecrets();
+}
+
+// Removes the key and its stored values in every profile.
+export async function unregisterCustomSecret(id: RuntimeSecretKey): Promise<void> {
+  await invokeTauri<void>('unregister_custom_secret', { id });
+  secretDescriptors = secretDescriptors.filter((descriptor) => descriptor.id !== id);
+  delete runtimeConfig.secrets[id];
+  notifyConfigChanged();
+}
+
+export interface SecretProfiles {
+  active: string;
+  profiles: string[];
+}
+
+export async function listSecretProfiles(): Promise<SecretProfiles | null> {
+  if (!isDesktopRuntime()) return null;
+  return invokeTauri<SecretProfiles>('list_secret_profiles');
+}
+
+// `restartError` is set when the profile switched but the sidecar couldn't be restarted with it.
+export interface SecretProfileSwitch extends SecretProfiles {
+  restartError: string | null;
+}
+
+// Creates the profile if needed; the shell restarts the sidecar with that profile's keys.
+export async function switchSecretProfile(name: string): Promise<SecretProfileSwitch> {
+  const profiles = await invokeTauri<SecretProfileSwitch>('switch_secret_profile', { name });
+  await loadDesktopSecrets();
+  return profiles;
+}
+
+export async function deleteSecretProfile(name: string): Promise<SecretProfiles> {
+  return invokeTauri<SecretProfiles>('delete_secret_profile', { name });
+}
+
+export interface BundlePreview {
+  path: string;
+  createdAt: number;
+  profile: string;
+  keys: Array<{ key: string; overwrite: boolean; unchanged: boolean; unsupported: boolean }>;
+  featureToggles: Partial<Record<RuntimeFeatureId, boolean>>;
+}
+
+// Resolves to the written path, or null if the save dialog was cancelled.
+export async function exportSecretsBundle(passphrase: string): Promise<string | null> {
+  return invokeTauri<string | null>('export_secrets_bundle', {
+    passphrase,
+    featureToggles: runtimeConfig.featureToggles,
+  });
+}
+
+// Decrypts a bundle picked in a native dialog without applying it; null if cancelled.
+export async function previewSecretsBundle(passphrase: string): Promise<BundlePreview | null> {
+  return invokeTauri<BundlePreview | null>('preview_secrets_bundle', { passphrase });
+}
+
+// Applies the previewed bundle (optionally only `keys`) and its feature toggles.
+export async function importSecretsBundle(keys?: RuntimeSecretKey[]): Promise<void> {
+  const result = await invokeTauri<{ secrets: SecretBatchResult[]; featureToggles: Partial<Record<RuntimeFeatureId, boolean>> }>(
+    'import_secrets_bundle',
+    { keys: keys ?? null },
+  );
+  const failures = result.secrets.filter((entry) => !entry.ok);
+  for (const entry of result.secrets) {
+    if (entry.status) applySecretStatus(entry.status);
+  }
+  for (const feature of RUNTIME_FEATURES) {
+    const enabled = result.featureToggles?.[feature.id];
+    if (typeof enabled === 'boolean') runtimeConfig.featureToggles[feature.id] = enabled;
+  }
+  localStorage.setItem(TOGGLES_STORAGE_KEY, JSON.stringify(runtimeConfig.featureToggles));
+  notifyConfigChanged();
+  if (failures.length > 0) {
+    throw new Error(failures.map((entry) => `${entry.key}: ${entry.error ?? 'failed'}`).join('; '));
+  }
+}
+
+// Omit `value` to validate the stored secret; `probe` makes one authenticated request to the provider.
+export async function validateSecret(key: RuntimeSecretKey, value?: string, probe = true): Promise<SecretVerdict> {
+  return invokeTauri<SecretVerdict>('validate_secret', { key, value: value?.trim() || null, probe });
 }
 
 export async function loadDesktopSecrets(): Promise<void> {
   if (!isDesktopRuntime()) return;
 
   try {
-    const keys = await invokeTauri<RuntimeSecretKey[]>('list_supported_secret_keys');
-
-    await Promise.all(keys.map(async (key) => {
-      const value = await invokeTauri<string | null>('get_secret', { key });
-      if (value && value.trim()) {
-        runtimeConfig.secrets[key] = { value: value.trim(), source: 'vault' };
-        pushSecretToSidecar(key, value.trim());
+    secretDescriptors = await invokeTauri<SecretDescriptor[]>('list_supported_secret_keys');
+
+    const results = await invokeTauri<SecretBatchResult[]>('get_secrets_status', {
+      keys: secretDescriptors.map((descriptor) => descriptor.id),
+    });
+    for (const result of results) {
+      if (result.status) {
+        applySecretStatus(result.status);
+      } else {
+        console.warn(`[runtime-config] Failed to read secret ${result.key}`, result.error);
       }
-    }));
+    }
 
     notifyConfigChanged();
   } catch (error) {
@@ -4,9 +4,12 @@ const DEFAULT_REMOTE_HOSTS: Record<string, string> = {
   world: 'https://worldmonitor.app',
 };
 
-const DEFAULT_LOCAL_API_BASE = 'http://127.0.0.1:46123';
 const FORCE_DESKTOP_RUNTIME = import.meta.env.VITE_DESKTOP_RUNTIME === '1';
 
+// The desktop shell allocates the sidecar port at launch; resolved lazily via IPC. Until the
+// shell has reported it there is no local base, and `/api/` requests are answered as unavailable.
+let resolvedLocalApiBase: string | null = null;
+
 function normalizeBaseUrl(baseUrl: string): string {
   return baseUrl.replace(/\/$/, '');
 }
@@ -72,7 +75,23 @@ export function getApiBaseUrl(): string {
     return normalizeBaseUrl(configuredBaseUrl);
   }
 
-  return DEFAULT_LOCAL_API_BASE;
+  return resolvedLocalApiBase ?? '';
+}
+
+export async function resolveLocalApiBaseUrl(): Promise<string> {
+  if (!isDesktopRuntime() || import.meta.env.VITE_TAURI_API_BASE_URL || resolvedLocalApiBase) {
+    return getApiBaseUrl();
+  }
+
+  try {
+    const { tryInvokeTauri } = await import('@/services/tauri-bridge');
+    const port = await tryInvokeTauri<number>('get_local_api_port');
+    if (port) {
+      resolvedLocalApiBase = `http://127.0.0.1:${port}`;
+    }
+  } catch { /* port unavailable — no local base */ }
+
+  return getApiBaseUrl();
 }
 
 export function getRemoteApiBaseUrl(): string {
@@ -183,8 +202,35 @@ export function installRuntimeFetchPatch(): void {
   }
 
   const nativeFetch = window.fetch.bind(window);
-  const localBase = getApiBaseUrl();
+  const localBasePromise = resolveLocalApiBaseUrl();
   let localApiToken: string | null = null;
+  let localApiTokenRequest: Promise<void> | null = null;
+
+  // The shell rotates the token whenever the sidecar restarts, and per-window tokens expire,
+  // so a 401 means our copy is stale rather than that we are locked out. The shell also withholds
+  // the token until the sidecar has passed its identity check, so wait briefly for that.
+  const loadLocalApiToken = (): Promise<void> => {
+    localApiTokenRequest ??= (async () => {
+      const { invokeTauri } = await import('@/services/tauri-bridge');
+      for (let attempt = 1; attempt <= 20; attempt += 1) {
+        try {
+          localApiToken = await invokeTauri<string>('get_local_api_token');
+          return;
+        } catch { /* identity not verified yet, or token unavailable — sidecar may not require it */ }
+        await sleep(500);
+      }
+    })().finally(() => {
+      localApiTokenRequest = null;
+    });
+    return localApiTokenRequest;
+  };
+  const withLocalApiToken = (init?: RequestInit): RequestInit => {
+    const headers = new Headers(init?.headers);
+    if (localApiToken) {
+      headers.set('Authorization', `Bearer ${localApiToken}`);
+    }
+    return { ...init, headers };
+  };
 
   window.fetch = async (input: RequestInfo | URL, init?: RequestInit): Promise<Response> => {
     const target = getApiTargetFromRequestInput(input);
@@ -199,24 +245,21 @@ export function installRuntimeFetchPatch(): void {
     }
 
     if (!localApiToken) {
-      try {
-        const { tryInvokeTauri } = await import('@/services/tauri-bridge');
-        localApiToken = await tryInvokeTauri<string>('get_local_api_token');
-      } catch { /* token unavailable — sidecar may not require it */ }
-    }
-
-    const headers = new Headers(init?.headers);
-    if (localApiToken) {
-      headers.set('Authorization', `Bearer ${localApiToken}`);
+      await loadLocalApiToken();
     }
-    const localInit = { ...init, headers };
 
+    const localBase = await localBasePromise;
     const localUrl = `${localBase}${target}`;
     if (debug) console.log(`[fetch] intercept → ${target}`);
 
     try {
+      if (!localBase) throw new Error('local API port not reported by the shell');
       const t0 = performance.now();
-      const response = await fetchLocalWithStartupRetry(nativeFetch, localUrl, localInit);
+      let response = await fetchLocalWithStartupRetry(nativeFetch, localUrl, withLocalApiToken(init));
+      if (response.status === 401) {
+        await loadLocalApiToken();
+        response = await fetchLocalWithStartupRetry(nativeFetch, localUrl, withLocalApiToken(init));
+      }
       if (debug) console.log(`[fetch] ${target} → ${response.status} (${Math.round(performance.now() - t0)}ms)`);
       return response;
     } catch (error) {
@@ -1,4 +1,10 @@
 type TauriInvoke = <T>(command: string, payload?: Record<string, unknown>) => Promise<T>;
+type TauriTransformCallback = (callback: (message: unknown) => void, once?: boolean) => number;
+
+export type TauriUnlisten = () => void;
+
+// Which emitters a listener accepts; mirrors `EventTarget` in `@tauri-apps/api/event`.
+export type TauriEventTarget = { kind: 'Any' } | { kind: 'WebviewWindow'; label: string };
 
 function resolveInvokeBridge(): TauriInvoke | null {
   if (typeof window === 'undefined') {
@@ -44,3 +50,41 @@ export async function tryInvokeTauri<T>(
     return null;
   }
 }
+
+/**
+ * Subscribes to a Tauri event emitted by the Rust shell. Mirrors `@tauri-apps/api/event`'s
+ * `listen` on top of the raw IPC bridge so the app doesn't need the JS package.
+ */
+export async function listenTauri<T>(
+  event: string,
+  handler: (payload: T) => void,
+  target: TauriEventTarget = { kind: 'Any' },
+): Promise<TauriUnlisten | null> {
+  if (typeof window === 'undefined') {
+    return null;
+  }
+
+  const tauriWindow = window as unknown as {
+    __TAURI_INTERNALS__?: { transformCallback?: TauriTransformCallback };
+  };
+  const transformCallback = tauriWindow.__TAURI_INTERNALS__?.transformCallback;
+  if (typeof transformCallback !== 'function') {
+    return null;
+  }
+
+  const callbackId = transformCallback((message) => {
+    handler((message as { payload: T }).payload);
+  });
+  const eventId = await tryInvokeTauri<number>('plugin:event|listen', {
+    event,
+    target,
+    handler: callbackId,
+  });
+  if (eventId === null) {
+    return null;
+  }
+
+  return () => {
+    void tryInvokeTauri<void>('plugin:event|unlisten', { event, eventId });
+  };
+}
@@ -1,7 +1,24 @@
 import './styles/main.css';
 import './styles/settings-window.css';
 import { RuntimeConfigPanel } from '@/components/RuntimeConfigPanel';
-import { loadDesktopSecrets } from '@/services/runtime-config';
+import {
+  exportSecretsBundle,
+  getSecretStoreStatus,
+  importSecretsBundle,
+  listSecretProfiles,
+  loadDesktopSecrets,
+  previewSecretsBundle,
+  switchSecretProfile,
+  unlockSecretVault,
+  type BundlePreview,
+  type SecretProfiles,
+} from '@/services/runtime-config';
+import {
+  clearPersistentCache,
+  getPersistentCacheStats,
+  subscribePersistentCacheChanges,
+} from '@/services/persistent-cache';
+import { resolveLocalApiBaseUrl } from '@/services/runtime';
 import { tryInvokeTauri } from '@/services/tauri-bridge';
 import { escapeHtml } from '@/utils/sanitize';
 
@@ -61,8 +78,207 @@ function closeSettingsWindow(): void {
   void tryInvokeTauri<void>('close_settings_window').then(() => {}, () => window.close());
 }
 
+// Without an OS keyring, secrets live in a passphrase-encrypted file that must be unlocked first.
+async function ensureSecretVaultUnlocked(): Promise<void> {
+  const status = await getSecretStoreStatus().catch(() => null);
+  if (!status?.locked) return;
+
+  const section = document.getElementById('vaultUnlock');
+  const message = document.getElementById('vaultUnlockMessage');
+  const form = document.getElementById('vaultUnlockForm') as HTMLFormElement | null;
+  const input = document.getElementById('vaultPassphrase') as HTMLInputElement | null;
+  if (!section || !form || !input) return;
+
+  if (message) {
+    message.textContent = status.vaultExists
+      ? `Secrets are stored in an encrypted file (${status.reason}). Enter the vault passphrase to unlock it.`
+      : `No OS keyring is available (${status.reason}). Choose a passphrase to create an encrypted secret vault.`;
+  }
+  section.hidden = false;
+  input.focus();
+
+  await new Promise<void>((resolve) => {
+    form.addEventListener('submit', (event) => {
+      event.preventDefault();
+      void unlockSecretVault(input.value)
+        .then(() => {
+          input.value = '';
+          section.hidden = true;
+          setActionStatus('Secret vault unlocked', 'ok');
+          resolve();
+        })
+        .catch((error) => {
+          setActionStatus(`Unlock failed: ${error}`, 'error');
+          input.select();
+        });
+    });
+  });
+}
+
+function renderSecretProfiles(profiles: SecretProfiles): void {
+  const input = document.getElementById('secretProfileName') as HTMLInputElement | null;
+  const options = document.getElementById('secretProfileNames');
+  if (input) input.value = profiles.active;
+  if (options) {
+    options.innerHTML = profiles.profiles.map((name) => `<option value="${escapeHtml(name)}"></option>`).join('');
+  }
+}
+
+// Each profile is a separate set of keys; typing a new name creates an empty one.
+async function initSecretProfiles(): Promise<void> {
+  const form = document.getElementById('secretProfileForm') as HTMLFormElement | null;
+  const input = document.getElementById('secretProfileName') as HTMLInputElement | null;
+  const profiles = await listSecretProfiles().catch(() => null);
+  if (!form || !input || !profiles) return;
+
+  renderSecretProfiles(profiles);
+  form.hidden = false;
+  form.addEventListener('submit', (event) => {
+    event.preventDefault();
+    const name = input.value.trim();
+    if (!name) return;
+    void switchSecretProfile(name)
+      .then((updated) => {
+        renderSecretProfiles(updated);
+        if (updated.restartError) {
+          setActionStatus(`Switched to key profile "${updated.active}", but the local API failed to restart: ${updated.restartError}`, 'error');
+        } else {
+          setActionStatus(`Switched to key profile "${updated.active}"`, 'ok');
+        }
+      })
+      .catch((error) => setActionStatus(`Profile switch failed: ${error}`, 'error'));
+  });
+}
+
+function describeBundleKey(entry: BundlePreview['keys'][number]): string {
+  if (entry.unsupported) return 'not supported here, skipped';
+  if (entry.overwrite) return 'will overwrite the stored key';
+  if (entry.unchanged) return 'already stored';
+  return 'new';
+}
+
+// Export/import of a passphrase-encrypted bundle; the shell shows the native file dialogs.
+function initSecretTransfer(): void {
+  const section = document.getElementById('secretTransfer');
+  const passphrase = document.getElementById('bundlePassphrase') as HTMLInputElement | null;
+  const preview = document.getElementById('bundlePreview');
+  const summary = document.getElementById('bundlePreviewSummary');
+  const keyList = document.getElementById('bundlePreviewKeys');
+  if (!section || !passphrase || !preview || !summary || !keyList) return;
+  section.hidden = false;
+
+  const requirePassphrase = (): string | null => {
+    if (passphrase.value) return passphrase.value;
+    setActionStatus('Enter a bundle passphrase first', 'error');
+    passphrase.focus();
+    return null;
+  };
+
+  document.getElementById('exportBundleBtn')?.addEventListener('click', () => {
+    const value = requirePassphrase();
+    if (!value) return;
+    void exportSecretsBundle(value)
+      .then((path) => {
+        if (!path) return;
+        passphrase.value = '';
+        setActionStatus(`Exported keys to ${path}`, 'ok');
+      })
+      .catch((error) => setActionStatus(`Export failed: ${error}`, 'error'));
+  });
+
+  document.getElementById('importBundleBtn')?.addEventListener('click', () => {
+    const value = requirePassphrase();
+    if (!value) return;
+    void previewSecretsBundle(value)
+      .then((bundle) => {
+        if (!bundle) return;
+        passphrase.value = '';
+        const created = new Date(bundle.createdAt).toLocaleString();
+        summary.textContent = `${bundle.path} — profile "${bundle.profile}", exported ${created}. ${bundle.keys.length} keys:`;
+        keyList.innerHTML = bundle.keys
+          .map((entry) => `<li class="${entry.overwrite ? 'overwrite' : ''}"><code>${escapeHtml(entry.key)}</code> — ${describeBundleKey(entry)}</li>`)
+          .join('');
+        preview.hidden = false;
+      })
+      .catch((error) => setActionStatus(`Import failed: ${error}`, 'error'));
+  });
+
+  document.getElementById('confirmImportBtn')?.addEventListener('click', () => {
+    void importSecretsBundle()
+      .then(() => setActionStatus('Imported keys and feature toggles', 'ok'))
+      .catch((error) => setActionStatus(`Import failed: ${error}`, 'error'))
+      .finally(() => {
+        preview.hidden = true;
+      });
+  });
+
+  document.getElementById('cancelImportBtn')?.addEventListener('click', () => {
+    preview.hidden = true;
+  });
+}
+
+function formatBytes(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
+async function refreshCacheStats(): Promise<void> {
+  const statsEl = document.getElementById('cacheStats');
+  if (!statsEl) return;
+  try {
+    const stats = await getPersistentCacheStats();
+    statsEl.textContent = `${stats.entries} cached entries, ${formatBytes(stats.totalBytes)} of ${formatBytes(stats.maxBytes)}`;
+    statsEl.title = stats.path;
+    if (stats.recovery) {
+      setActionStatus(
+        `Cache was corrupt and ${stats.recovery.restoredFromBackup ? 'restored from backup' : 'reset'}; damaged copy kept at ${stats.recovery.quarantined}`,
+        'error',
+      );
+    }
+  } catch {
+    statsEl.textContent = '';
+  }
+}
+
+// Clearing only drops cached panel payloads; panels refetch on their next refresh.
+function initCacheControls(): void {
+  document.getElementById('clearCacheBtn')?.addEventListener('click', () => {
+    void clearPersistentCache()
+      .then((count) => setActionStatus(`Cleared ${count} cached entries`, 'ok'))
+      .catch((error) => setActionStatus(`Clearing cache failed: ${error}`, 'error'))
+      .finally(() => void refreshCacheStats());
+  });
+  void refreshCacheStats();
+  // Keep the counts current while other windows write to the cache.
+  void subscribePersistentCacheChanges(() => void refreshCacheStats()).then((unlisten) => {
+    if (unlisten) window.addEventListener('beforeunload', unlisten);
+  });
+}
+
 async function initSettingsWindow(): Promise<void> {
+  // Cancel: discard pending, close
+  document.getElementById('cancelBtn')?.addEventListener('click', () => {
+    closeSettingsWindow();
+  });
+
+  const openLogsBtn = document.getElementById('openLogsBtn');
+  openLogsBtn?.addEventListener('click', () => {
+    void invokeDesktopAction('open_logs_folder', 'Opened logs folder');
+  });
+
+  const openSidecarLogBtn = document.getElementById('openSidecarLogBtn');
+  openSidecarLogBtn?.addEventListener('click', () => {
+    void invokeDesktopAction('open_sidecar_log_file', 'Opened API log');
+  });
+
+  initTabs();
+  initCacheControls();
+
+  await ensureSecretVaultUnlocked();
   await loadDesktopSecrets();
+  await initSecretProfiles();
+  initSecretTransfer();
 
   const mount = document.getElementById('settingsApp');
   if (!mount) return;
@@ -87,26 +303,11 @@ async function initSettingsWindow(): Promise<void> {
       }
     })();
   });
-
-  // Cancel: discard pending, close
-  document.getElementById('cancelBtn')?.addEventListener('click', () => {
-    closeSettingsWindow();
-  });
-
-  const openLogsBtn = document.getElementById('openLogsBtn');
-  openLogsBtn?.addEventListener('click', () => {
-    void invokeDesktopAction('open_logs_folder', 'Opened logs folder');
-  });
-
-  const openSidecarLogBtn = document.getElementById('openSidecarLogBtn');
-  openSidecarLogBtn?.addEventListener('click', () => {
-    void invokeDesktopAction('open_sidecar_log_file', 'Opened API log');
-  });
-
-  initTabs();
 }
 
-const SIDECAR_BASE = 'http://127.0.0.1:46123';
+async function sidecarUrl(path: string): Promise<string> {
+  return `${await resolveLocalApiBaseUrl()}${path}`;
+}
 
 function initDiagnostics(): void {
   const verboseToggle = document.getElementById('verboseApiLog') as HTMLInputElement | null;
@@ -127,7 +328,7 @@ function initDiagnostics(): void {
   async function syncVerboseState(): Promise<void> {
     if (!verboseToggle) return;
     try {
-      const res = await fetch(`${SIDECAR_BASE}/api/local-debug-toggle`);
+      const res = await fetch(await sidecarUrl('/api/local-debug-toggle'));
       const data = await res.json();
       verboseToggle.checked = data.verboseMode;
     } catch { /* sidecar not running */ }
@@ -135,7 +336,7 @@ function initDiagnostics(): void {
 
   verboseToggle?.addEventListener('change', async () => {
     try {
-      const res = await fetch(`${SIDECAR_BASE}/api/local-debug-toggle`, { method: 'POST' });
+      const res = await fetch(await sidecarUrl('/api/local-debug-toggle'), { method: 'POST' });
       const data = await res.json();
       if (verboseToggle) verboseToggle.checked = data.verboseMode;
       setActionStatus(data.verboseMode ? 'Verbose sidecar logging ON (saved)' : 'Verbose sidecar logging OFF (saved)', 'ok');
@@ -149,7 +350,7 @@ function initDiagnostics(): void {
   async function refreshTrafficLog(): Promise<void> {
     if (!trafficLogEl) return;
     try {
-      const res = await fetch(`${SIDECAR_BASE}/api/local-traffic-log`);
+      const res = await fetch(await sidecarUrl('/api/local-traffic-log'));
       const data = await res.json();
       const entries: Array<{ timestamp: string; method: string; path: string; status: number; durationMs: number }> = data.entries || [];
       if (trafficCount) trafficCount.textContent = `(${entries.length})`;
@@ -175,7 +376,7 @@ function initDiagnostics(): void {
 
   clearBtn?.addEventListener('click', async () => {
     try {
-      await fetch(`${SIDECAR_BASE}/api/local-traffic-log`, { method: 'DELETE' });
+      await fetch(await sidecarUrl('/api/local-traffic-log'), { method: 'DELETE' });
     } catch { /* ignore */ }
     if (trafficLogEl) trafficLogEl.innerHTML = '<p class="diag-empty">Log cleared.</p>';
     if (trafficCount) trafficCount.textContent = '(0)';